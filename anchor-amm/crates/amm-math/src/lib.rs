@@ -0,0 +1,206 @@
+//! Off-chain quoting and simulation for anchor-amm-q4-25 pools.
+//!
+//! A [`Pool`] is loaded from the raw `config`, vault and LP mint account data and
+//! replays `deposit`, `withdraw` and `swap` with the same curve, fee and rounding
+//! as the program, so a quote is exactly what the transaction will do on-chain.
+//! The `quote_*` methods leave the pool untouched, the plain methods apply the
+//! instruction to the local state so sequences of trades can be simulated.
+
+use anchor_amm_q4_25::{
+    errors::AmmError,
+    state::{Config, Partner},
+};
+use anchor_lang::prelude::*;
+use anchor_spl::{token::TokenAccount, token_interface::Mint};
+use constant_product_curve::{ConstantProduct, LiquidityPair};
+
+pub use anchor_amm_q4_25::ID as PROGRAM_ID;
+
+#[derive(Clone)]
+pub struct Pool {
+    pub config: Config,
+    pub vault_x: u64,   // Token X held by the vault, including unsynced donations
+    pub vault_y: u64,   // Token Y held by the vault, including unsynced donations
+    pub lp_supply: u64, // Outstanding LP tokens
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapQuote {
+    pub amount_in: u64,  // Tokens taken from the user, fee included
+    pub amount_out: u64, // Tokens sent to the user
+    pub fee: u16,        // Fee charged in basis points
+    pub referral: u64,   // Part of amount_in paid to the referrer instead of the pool
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LiquidityQuote {
+    pub lp: u64, // LP tokens minted or burned
+    pub x: u64,  // Token X deposited or withdrawn
+    pub y: u64,  // Token Y deposited or withdrawn
+}
+
+impl Pool {
+    pub fn new(config: Config, vault_x: u64, vault_y: u64, lp_supply: u64) -> Self {
+        Self {
+            config,
+            vault_x,
+            vault_y,
+            lp_supply,
+        }
+    }
+
+    pub fn from_account_data(
+        config: &[u8],
+        vault_x: &[u8],
+        vault_y: &[u8],
+        mint_lp: &[u8],
+    ) -> Result<Self> {
+        let config = Config::try_deserialize(&mut &config[..])?;
+        let vault_x = TokenAccount::try_deserialize(&mut &vault_x[..])?;
+        let vault_y = TokenAccount::try_deserialize(&mut &vault_y[..])?;
+        let mint_lp = Mint::try_deserialize(&mut &mint_lp[..])?;
+
+        require_keys_eq!(vault_x.mint, config.mint_x, AmmError::InvalidToken);
+        require_keys_eq!(vault_y.mint, config.mint_y, AmmError::InvalidToken);
+
+        Ok(Self::new(config, vault_x.amount, vault_y.amount, mint_lp.supply))
+    }
+
+    // Vault balances not accounted to LPs, i.e. what `skim` would send out
+    pub fn excess(&self) -> (u64, u64) {
+        (
+            self.vault_x.saturating_sub(self.config.reserve_x),
+            self.vault_y.saturating_sub(self.config.reserve_y),
+        )
+    }
+
+    pub fn quote_swap(
+        &self,
+        is_x: bool,
+        amount: u64,
+        min: u64,
+        now: i64,
+        partner: Option<&Partner>,
+    ) -> Result<SwapQuote> {
+        let mut partner = partner.cloned();
+        self.clone().swap(is_x, amount, min, now, partner.as_mut())
+    }
+
+    pub fn quote_deposit(&self, amount: u64, max_x: u64, max_y: u64) -> Result<LiquidityQuote> {
+        self.clone().deposit(amount, max_x, max_y)
+    }
+
+    pub fn quote_withdraw(&self, amount: u64, min_x: u64, min_y: u64) -> Result<LiquidityQuote> {
+        self.clone().withdraw(amount, min_x, min_y)
+    }
+
+    // Mirrors `Swap::swap`, including the referral taken out of the fee
+    pub fn swap(
+        &mut self,
+        is_x: bool,
+        amount: u64,
+        min: u64,
+        now: i64,
+        partner: Option<&mut Partner>,
+    ) -> Result<SwapQuote> {
+        require!(!self.config.locked, AmmError::PoolLocked);
+        require!(amount > 0, AmmError::InvalidAmount);
+
+        let fee = self.config.swap_fee(now);
+        let mut curve = ConstantProduct::init(
+            self.config.reserve_x,
+            self.config.reserve_y,
+            self.lp_supply,
+            fee,
+            None,
+        )
+        .map_err(AmmError::from)?;
+        let pair = match is_x {
+            true => LiquidityPair::X,
+            false => LiquidityPair::Y,
+        };
+        let swaps = curve.swap(pair, amount, min).map_err(AmmError::from)?;
+        require!(swaps.deposit != 0, AmmError::InvalidAmount);
+
+        let referral = partner
+            .map(|partner| partner.take_referral(is_x, swaps.deposit, fee))
+            .unwrap_or(0);
+        let deposit = swaps.deposit - referral;
+        let withdraw = swaps.withdraw.min(self.config.max_swap_out(is_x, deposit)?);
+        require!(withdraw != 0, AmmError::InvalidAmount);
+        require!(withdraw >= min, AmmError::SlippageExceeded);
+
+        let (x, y) = match is_x {
+            true => (
+                self.config.reserve_x.checked_add(deposit).ok_or(AmmError::Overflow)?,
+                self.config.reserve_y.checked_sub(withdraw).ok_or(AmmError::Underflow)?,
+            ),
+            false => (
+                self.config.reserve_x.checked_sub(withdraw).ok_or(AmmError::Underflow)?,
+                self.config.reserve_y.checked_add(deposit).ok_or(AmmError::Overflow)?,
+            ),
+        };
+        let (vault_in, vault_out) = match is_x {
+            true => (&mut self.vault_x, &mut self.vault_y),
+            false => (&mut self.vault_y, &mut self.vault_x),
+        };
+        *vault_in = vault_in.checked_add(deposit).ok_or(AmmError::Overflow)?;
+        *vault_out = vault_out.checked_sub(withdraw).ok_or(AmmError::Underflow)?;
+        self.config.reserve_x = x;
+        self.config.reserve_y = y;
+        self.config.record_price(x, y, now);
+
+        Ok(SwapQuote {
+            amount_in: swaps.deposit,
+            amount_out: withdraw,
+            fee,
+            referral,
+        })
+    }
+
+    // Mirrors `Deposit::deposit`
+    pub fn deposit(&mut self, amount: u64, max_x: u64, max_y: u64) -> Result<LiquidityQuote> {
+        require!(!self.config.locked, AmmError::PoolLocked);
+        require!(amount != 0, AmmError::InvalidAmount);
+
+        let (x, y) = match self.lp_supply == 0
+            && self.config.reserve_x == 0
+            && self.config.reserve_y == 0
+        {
+            true => (max_x, max_y),
+            false => self.config.deposit_amounts(self.lp_supply, amount)?,
+        };
+
+        require!(x <= max_x && y <= max_y, AmmError::SlippageExceeded);
+
+        self.config.reserve_x = self.config.reserve_x.checked_add(x).ok_or(AmmError::Overflow)?;
+        self.config.reserve_y = self.config.reserve_y.checked_add(y).ok_or(AmmError::Overflow)?;
+        self.vault_x = self.vault_x.checked_add(x).ok_or(AmmError::Overflow)?;
+        self.vault_y = self.vault_y.checked_add(y).ok_or(AmmError::Overflow)?;
+        self.lp_supply = self.lp_supply.checked_add(amount).ok_or(AmmError::Overflow)?;
+
+        Ok(LiquidityQuote { lp: amount, x, y })
+    }
+
+    // Mirrors `Withdraw::withdraw`, minus the check on the user's LP balance
+    pub fn withdraw(&mut self, amount: u64, min_x: u64, min_y: u64) -> Result<LiquidityQuote> {
+        require!(!self.config.locked, AmmError::PoolLocked);
+        require!(amount > 0, AmmError::InvalidAmount);
+        require!(self.lp_supply >= amount, AmmError::InsufficientBalance);
+
+        let (x, y) = match self.lp_supply == 0 {
+            true => (0u64, 0u64),
+            false => self.config.withdraw_amounts(self.lp_supply, amount)?,
+        };
+
+        require!(x >= min_x && y >= min_y, AmmError::SlippageExceeded);
+
+        self.config.reserve_x = self.config.reserve_x.checked_sub(x).ok_or(AmmError::Underflow)?;
+        self.config.reserve_y = self.config.reserve_y.checked_sub(y).ok_or(AmmError::Underflow)?;
+        self.vault_x = self.vault_x.checked_sub(x).ok_or(AmmError::Underflow)?;
+        self.vault_y = self.vault_y.checked_sub(y).ok_or(AmmError::Underflow)?;
+        self.lp_supply -= amount;
+
+        Ok(LiquidityQuote { lp: amount, x, y })
+    }
+}
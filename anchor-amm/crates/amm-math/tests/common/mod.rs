@@ -0,0 +1,113 @@
+#![allow(dead_code)]
+
+use amm_math::Pool;
+use anchor_amm_q4_25::state::{Config, DynamicFee};
+use anchor_lang::prelude::*;
+use proptest::prelude::*;
+
+// Large enough that deposits never hit the slippage limits
+pub const MAX_DEPOSIT: u64 = 1_000_000_000_000_000;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Op {
+    Deposit { lp: u64 },
+    Withdraw { lp_bps: u16 }, // Share of the outstanding LP supply to burn
+    Swap { is_x: bool, amount: u64 },
+}
+
+pub fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        (1u64..1_000_000_000).prop_map(|lp| Op::Deposit { lp }),
+        (1u16..=10_000).prop_map(|lp_bps| Op::Withdraw { lp_bps }),
+        (any::<bool>(), 1u64..1_000_000_000).prop_map(|(is_x, amount)| Op::Swap { is_x, amount }),
+    ]
+}
+
+// No dynamic fee, or bounds and a decay period valid for `set_dynamic_fee`
+pub fn dynamic_fee() -> impl Strategy<Value = Option<DynamicFee>> {
+    prop::option::of((0u16..500, 0u16..500, 0u16..=10_000, 1u32..3_600).prop_map(
+        |(min_fee, spread, volatility_factor, decay_period)| DynamicFee {
+            min_fee,
+            max_fee: min_fee + spread,
+            volatility_factor,
+            decay_period,
+        },
+    ))
+}
+
+pub fn withdraw_amount(pool: &Pool, lp_bps: u16) -> u64 {
+    (pool.lp_supply as u128 * lp_bps as u128 / 10_000) as u64
+}
+
+pub fn empty_pool(fee: u16) -> Pool {
+    let config = Config {
+        seed: 0,
+        authority: None,
+        mint_x: Pubkey::new_unique(),
+        mint_y: Pubkey::new_unique(),
+        fee,
+        reserve_x: 0,
+        reserve_y: 0,
+        dynamic_fee: None,
+        volatility: 0,
+        last_price: 0,
+        last_swap: 0,
+        locked: false,
+        config_bump: 0,
+        lp_bump: 0,
+    };
+    Pool::new(config, 0, 0, 0)
+}
+
+// Applies an op the way a transaction would: all or nothing
+pub fn apply(pool: &mut Pool, op: Op, now: i64) -> Result<()> {
+    let mut next = pool.clone();
+    match op {
+        Op::Deposit { lp } => next.deposit(lp, MAX_DEPOSIT, MAX_DEPOSIT).map(|_| ())?,
+        Op::Withdraw { lp_bps } => next
+            .withdraw(withdraw_amount(pool, lp_bps), 0, 0)
+            .map(|_| ())?,
+        Op::Swap { is_x, amount } => next.swap(is_x, amount, 0, now, None).map(|_| ())?,
+    }
+    *pool = next;
+    Ok(())
+}
+
+fn k(pool: &Pool) -> u128 {
+    pool.config.reserve_x as u128 * pool.config.reserve_y as u128
+}
+
+// Reserves backing each LP token must never go down: a depositor pays at least
+// their pro-rata share and a withdrawer never gets more than theirs
+pub fn assert_lp_value_kept(before: &Pool, after: &Pool) {
+    if before.lp_supply == 0 || after.lp_supply == 0 {
+        return;
+    }
+    for (reserve_before, reserve_after) in [
+        (before.config.reserve_x, after.config.reserve_x),
+        (before.config.reserve_y, after.config.reserve_y),
+    ] {
+        assert!(
+            reserve_after as u128 * before.lp_supply as u128
+                >= reserve_before as u128 * after.lp_supply as u128,
+            "value per LP token decreased: {} / {} -> {} / {}",
+            reserve_before,
+            before.lp_supply,
+            reserve_after,
+            after.lp_supply,
+        );
+    }
+}
+
+pub fn assert_invariants(before: &Pool, after: &Pool, op: Op) {
+    assert!(after.vault_x >= after.config.reserve_x);
+    assert!(after.vault_y >= after.config.reserve_y);
+
+    match op {
+        Op::Swap { .. } => {
+            assert_eq!(before.lp_supply, after.lp_supply);
+            assert!(k(after) >= k(before), "k decreased: {} -> {}", k(before), k(after));
+        }
+        Op::Deposit { .. } | Op::Withdraw { .. } => assert_lp_value_kept(before, after),
+    }
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use common::{
+    apply, assert_invariants, assert_lp_value_kept, dynamic_fee, empty_pool, op, MAX_DEPOSIT,
+};
+use proptest::prelude::*;
+
+proptest! {
+    #[test]
+    fn invariants_hold_across_random_sequences(
+        fee in 0u16..1_000,
+        seed_x in 1_000u64..1_000_000_000_000,
+        seed_y in 1_000u64..1_000_000_000_000,
+        seed_lp in 1_000u64..1_000_000_000_000,
+        dynamic_fee in dynamic_fee(),
+        ops in prop::collection::vec((op(), 0i64..900), 1..50),
+    ) {
+        let mut pool = empty_pool(fee);
+        pool.config.dynamic_fee = dynamic_fee;
+        pool.deposit(seed_lp, seed_x, seed_y).unwrap();
+        prop_assert_eq!((pool.config.reserve_x, pool.config.reserve_y), (seed_x, seed_y));
+
+        // Seconds pass between ops so the dynamic fee both builds up and decays
+        let mut now = 0;
+        for (op, elapsed) in ops {
+            now += elapsed;
+            let before = pool.clone();
+            if apply(&mut pool, op, now).is_ok() {
+                assert_invariants(&before, &pool, op);
+            }
+        }
+    }
+
+    #[test]
+    fn full_withdrawal_returns_no_more_than_deposited(
+        fee in 0u16..1_000,
+        seed_x in 1_000u64..1_000_000_000_000,
+        seed_y in 1_000u64..1_000_000_000_000,
+        seed_lp in 1_000u64..1_000_000_000_000,
+        lp in 1u64..1_000_000_000,
+    ) {
+        let mut pool = empty_pool(fee);
+        pool.deposit(seed_lp, seed_x, seed_y).unwrap();
+
+        // A second LP joins and leaves straight away
+        let Ok(deposited) = pool.deposit(lp, MAX_DEPOSIT, MAX_DEPOSIT) else {
+            return Ok(());
+        };
+        let before = pool.clone();
+        if let Ok(withdrawn) = pool.withdraw(lp, 0, 0) {
+            prop_assert!(withdrawn.x <= deposited.x);
+            prop_assert!(withdrawn.y <= deposited.y);
+            assert_lp_value_kept(&before, &pool);
+        }
+    }
+}
@@ -0,0 +1,587 @@
+//! Runs random deposit/withdraw/swap sequences against the compiled program in
+//! LiteSVM, checking the pool invariants and that amm-math predicted each result.
+//! Also covers the time-dependent parts, the dynamic fee and the LP farm, by
+//! warping the clock.
+//!
+//! Needs the program binary from `anchor build` (`anchor test` builds it and runs
+//! this crate's tests) and the Metaplex token metadata program dumped by
+//! `anchor run dump-metadata`, the same file `anchor test` loads into the validator.
+
+mod common;
+
+use amm_math::{Pool, PROGRAM_ID};
+use anchor_amm_q4_25::{
+    accounts, instruction,
+    state::{Config, DynamicFee, Farm, StakePosition},
+};
+use anchor_lang::{
+    prelude::*,
+    solana_program::{instruction::Instruction, system_instruction},
+    InstructionData,
+};
+use anchor_spl::{
+    associated_token::{
+        get_associated_token_address, get_associated_token_address_with_program_id,
+        spl_associated_token_account::instruction::create_associated_token_account,
+    },
+    metadata::ID as METADATA_PROGRAM_ID,
+    token::{
+        spl_token::{self, instruction as token_instruction},
+        Mint,
+    },
+    token_2022::spl_token_2022,
+    token_interface,
+};
+use common::{assert_invariants, dynamic_fee, op, withdraw_amount, Op, MAX_DEPOSIT};
+use litesvm::LiteSVM;
+use proptest::prelude::*;
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+use solana_transaction::Transaction;
+
+const PROGRAM_PATH: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../../target/deploy/anchor_amm_q4_25.so"
+);
+// Same dump Anchor.toml loads into the validator's genesis for `anchor test`
+const METADATA_PROGRAM_PATH: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../../tests/fixtures/mpl_token_metadata.so"
+);
+
+struct Harness {
+    svm: LiteSVM,
+    user: Keypair,
+    mint_x: Pubkey,
+    mint_y: Pubkey,
+    config: Pubkey,
+    mint_lp: Pubkey,
+}
+
+impl Harness {
+    fn new(seed: u64, fee: u16) -> Self {
+        let mut svm = LiteSVM::new();
+        svm.add_program_from_file(PROGRAM_ID, PROGRAM_PATH)
+            .expect("program binary missing, run `anchor build`");
+        svm.add_program_from_file(METADATA_PROGRAM_ID, METADATA_PROGRAM_PATH)
+            .expect("token metadata program missing, run `anchor run dump-metadata`");
+
+        let user = Keypair::new();
+        svm.airdrop(&user.pubkey(), 100_000_000_000).unwrap();
+
+        let config =
+            Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &PROGRAM_ID).0;
+        let mint_lp = Pubkey::find_program_address(&[b"lp", config.as_ref()], &PROGRAM_ID).0;
+
+        let mut harness = Self {
+            svm,
+            user,
+            mint_x: Pubkey::default(),
+            mint_y: Pubkey::default(),
+            config,
+            mint_lp,
+        };
+        harness.mint_x = harness.create_funded_mint();
+        harness.mint_y = harness.create_funded_mint();
+        harness.initialize(seed, fee);
+        harness
+    }
+
+    fn send(&mut self, ixs: &[Instruction], extra_signers: &[&Keypair]) -> bool {
+        let mut signers = vec![&self.user];
+        signers.extend_from_slice(extra_signers);
+        let tx = Transaction::new_signed_with_payer(
+            ixs,
+            Some(&self.user.pubkey()),
+            &signers,
+            self.svm.latest_blockhash(),
+        );
+        let result = self.svm.send_transaction(tx);
+        // Identical instructions are common in random sequences
+        self.svm.expire_blockhash();
+        result.is_ok()
+    }
+
+    fn create_funded_mint(&mut self) -> Pubkey {
+        let mint = Keypair::new();
+        let user = self.user.pubkey();
+        let ixs = [
+            system_instruction::create_account(
+                &user,
+                &mint.pubkey(),
+                self.svm.minimum_balance_for_rent_exemption(Mint::LEN),
+                Mint::LEN as u64,
+                &spl_token::ID,
+            ),
+            token_instruction::initialize_mint2(&spl_token::ID, &mint.pubkey(), &user, None, 6)
+                .unwrap(),
+            create_associated_token_account(&user, &user, &mint.pubkey(), &spl_token::ID),
+            token_instruction::mint_to(
+                &spl_token::ID,
+                &mint.pubkey(),
+                &get_associated_token_address(&user, &mint.pubkey()),
+                &user,
+                &[],
+                MAX_DEPOSIT,
+            )
+            .unwrap(),
+        ];
+        assert!(self.send(&ixs, &[&mint]));
+        mint.pubkey()
+    }
+
+    fn initialize(&mut self, seed: u64, fee: u16) {
+        let lp_metadata = Pubkey::find_program_address(
+            &[b"metadata", METADATA_PROGRAM_ID.as_ref(), self.mint_lp.as_ref()],
+            &METADATA_PROGRAM_ID,
+        )
+        .0;
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: accounts::Initialize {
+                initializer: self.user.pubkey(),
+                mint_x: self.mint_x,
+                mint_y: self.mint_y,
+                mint_lp: self.mint_lp,
+                vault_x: get_associated_token_address(&self.config, &self.mint_x),
+                vault_y: get_associated_token_address(&self.config, &self.mint_y),
+                config: self.config,
+                lp_metadata,
+                metadata_x: None,
+                metadata_y: None,
+                token_program: spl_token::ID,
+                lp_token_program: spl_token_2022::ID,
+                associated_token_program: anchor_spl::associated_token::ID,
+                metadata_program: METADATA_PROGRAM_ID,
+                system_program: anchor_lang::system_program::ID,
+                rent: anchor_lang::solana_program::sysvar::rent::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::Initialize {
+                seed,
+                fee,
+                authority: Some(self.user.pubkey()),
+            }
+            .data(),
+        };
+        assert!(self.send(&[ix], &[]));
+    }
+
+    fn liquidity_accounts(&self) -> accounts::Deposit {
+        let user = self.user.pubkey();
+        accounts::Deposit {
+            user,
+            mint_x: self.mint_x,
+            mint_y: self.mint_y,
+            config: self.config,
+            mint_lp: self.mint_lp,
+            vault_x: get_associated_token_address(&self.config, &self.mint_x),
+            vault_y: get_associated_token_address(&self.config, &self.mint_y),
+            user_x: get_associated_token_address(&user, &self.mint_x),
+            user_y: get_associated_token_address(&user, &self.mint_y),
+            user_lp: get_associated_token_address_with_program_id(
+                &user,
+                &self.mint_lp,
+                &spl_token_2022::ID,
+            ),
+            token_program: spl_token::ID,
+            lp_token_program: spl_token_2022::ID,
+            system_program: anchor_lang::system_program::ID,
+            associated_token_program: anchor_spl::associated_token::ID,
+        }
+    }
+
+    fn execute(&mut self, op: Op, pool: &Pool) -> bool {
+        let deposit = self.liquidity_accounts();
+        let (accounts, data) = match op {
+            Op::Deposit { lp } => (
+                deposit.to_account_metas(None),
+                instruction::Deposit {
+                    amount: lp,
+                    max_x: MAX_DEPOSIT,
+                    max_y: MAX_DEPOSIT,
+                }
+                .data(),
+            ),
+            Op::Withdraw { lp_bps } => (
+                accounts::Withdraw {
+                    user: deposit.user,
+                    mint_x: deposit.mint_x,
+                    mint_y: deposit.mint_y,
+                    config: deposit.config,
+                    mint_lp: deposit.mint_lp,
+                    vault_x: deposit.vault_x,
+                    vault_y: deposit.vault_y,
+                    user_x: deposit.user_x,
+                    user_y: deposit.user_y,
+                    user_lp: deposit.user_lp,
+                    token_program: deposit.token_program,
+                    lp_token_program: deposit.lp_token_program,
+                    system_program: deposit.system_program,
+                    associated_token_program: deposit.associated_token_program,
+                }
+                .to_account_metas(None),
+                instruction::Withdraw {
+                    amount: withdraw_amount(pool, lp_bps),
+                    max_x: 0,
+                    max_y: 0,
+                }
+                .data(),
+            ),
+            Op::Swap { is_x, amount } => (
+                accounts::Swap {
+                    user: deposit.user,
+                    mint_x: deposit.mint_x,
+                    mint_y: deposit.mint_y,
+                    config: deposit.config,
+                    mint_lp: deposit.mint_lp,
+                    vault_x: deposit.vault_x,
+                    vault_y: deposit.vault_y,
+                    user_x: deposit.user_x,
+                    user_y: deposit.user_y,
+                    partner: None,
+                    referrer_token: None,
+                    token_program: deposit.token_program,
+                    system_program: deposit.system_program,
+                    associated_token_program: deposit.associated_token_program,
+                }
+                .to_account_metas(None),
+                instruction::Swap {
+                    is_x,
+                    amount_in: amount,
+                    min_amount_out: 0,
+                }
+                .data(),
+            ),
+        };
+
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts,
+            data,
+        };
+        self.send(&[ix], &[])
+    }
+
+    fn fetch<T: AccountDeserialize>(&self, address: &Pubkey) -> T {
+        let account = self.svm.get_account(address).unwrap();
+        T::try_deserialize(&mut &account.data[..]).unwrap()
+    }
+
+    // Reads either program's token accounts, the LP ones carry Token-2022 extensions
+    fn token_amount(&self, address: &Pubkey) -> u64 {
+        self.fetch::<token_interface::TokenAccount>(address).amount
+    }
+
+    fn pool(&self) -> Pool {
+        let config = self.svm.get_account(&self.config).unwrap();
+        let mint_lp = self.svm.get_account(&self.mint_lp).unwrap();
+        Pool::new(
+            Config::try_deserialize(&mut &config.data[..]).unwrap(),
+            self.token_amount(&get_associated_token_address(&self.config, &self.mint_x)),
+            self.token_amount(&get_associated_token_address(&self.config, &self.mint_y)),
+            // The LP mint carries a close authority extension, so it is longer than a legacy mint
+            token_interface::Mint::try_deserialize(&mut &mint_lp.data[..]).unwrap().supply,
+        )
+    }
+
+    fn now(&self) -> i64 {
+        self.svm.get_sysvar::<Clock>().unix_timestamp
+    }
+
+    fn set_dynamic_fee(&mut self, dynamic_fee: Option<DynamicFee>) -> bool {
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: accounts::SetDynamicFee {
+                authority: self.user.pubkey(),
+                config: self.config,
+            }
+            .to_account_metas(None),
+            data: instruction::SetDynamicFee { dynamic_fee }.data(),
+        };
+        self.send(&[ix], &[])
+    }
+
+    fn warp(&mut self, seconds: i64) {
+        let mut clock = self.svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += seconds;
+        self.svm.set_sysvar(&clock);
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn program_matches_simulation_and_keeps_invariants(
+        fee in 0u16..1_000,
+        seed_x in 1_000u64..1_000_000_000_000,
+        seed_y in 1_000u64..1_000_000_000_000,
+        seed_lp in 1_000u64..1_000_000_000_000,
+        dynamic_fee in dynamic_fee(),
+        ops in prop::collection::vec((op(), 0i64..900), 1..25),
+    ) {
+        let mut harness = Harness::new(7, fee);
+
+        // The first deposit takes max_x and max_y as-is, so seed it by hand
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: harness.liquidity_accounts().to_account_metas(None),
+            data: instruction::Deposit { amount: seed_lp, max_x: seed_x, max_y: seed_y }.data(),
+        };
+        prop_assert!(harness.send(&[ix], &[]));
+        prop_assert_eq!(harness.pool().lp_supply, seed_lp);
+        prop_assert!(harness.set_dynamic_fee(dynamic_fee));
+
+        for (op, elapsed) in ops {
+            harness.warp(elapsed);
+            let before = harness.pool();
+            let mut expected = before.clone();
+            let simulated = common::apply(&mut expected, op, harness.now()).is_ok();
+
+            let executed = harness.execute(op, &before);
+            prop_assert_eq!(executed, simulated, "{:?}", op);
+
+            let after = harness.pool();
+            prop_assert_eq!(after.lp_supply, expected.lp_supply);
+            prop_assert_eq!(after.vault_x, expected.vault_x);
+            prop_assert_eq!(after.vault_y, expected.vault_y);
+            prop_assert_eq!(after.config.reserve_x, expected.config.reserve_x);
+            prop_assert_eq!(after.config.reserve_y, expected.config.reserve_y);
+            prop_assert_eq!(after.config.volatility, expected.config.volatility);
+            prop_assert_eq!(after.config.last_price, expected.config.last_price);
+
+            if executed {
+                assert_invariants(&before, &after, op);
+            }
+        }
+    }
+}
+
+#[test]
+fn farm_pays_rewards_for_elapsed_time_up_to_the_vault_balance() {
+    const REWARD_RATE: u64 = 1_000;
+    const STAKE: u64 = 400_000;
+
+    let mut harness = Harness::new(9, 30);
+    let seed_deposit = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: harness.liquidity_accounts().to_account_metas(None),
+        data: instruction::Deposit {
+            amount: 1_000_000,
+            max_x: 1_000_000,
+            max_y: 1_000_000,
+        }
+        .data(),
+    };
+    assert!(harness.send(&[seed_deposit], &[]));
+
+    let user = harness.user.pubkey();
+    let reward_mint = harness.create_funded_mint();
+    let farm = Pubkey::find_program_address(
+        &[b"farm", harness.config.as_ref(), reward_mint.as_ref()],
+        &PROGRAM_ID,
+    )
+    .0;
+    let position =
+        Pubkey::find_program_address(&[b"position", farm.as_ref(), user.as_ref()], &PROGRAM_ID).0;
+    let farm_lp =
+        get_associated_token_address_with_program_id(&farm, &harness.mint_lp, &spl_token_2022::ID);
+    let reward_vault = get_associated_token_address(&farm, &reward_mint);
+    let user_reward = get_associated_token_address(&user, &reward_mint);
+    let user_lp = harness.liquidity_accounts().user_lp;
+
+    let ix = |accounts: Vec<AccountMeta>, data: Vec<u8>| Instruction {
+        program_id: PROGRAM_ID,
+        accounts,
+        data,
+    };
+    // UnstakeLp takes the same accounts
+    let stake_accounts = accounts::StakeLp {
+        user,
+        config: harness.config,
+        mint_lp: harness.mint_lp,
+        farm,
+        position,
+        farm_lp,
+        user_lp,
+        lp_token_program: spl_token_2022::ID,
+        associated_token_program: anchor_spl::associated_token::ID,
+        system_program: anchor_lang::system_program::ID,
+    }
+    .to_account_metas(None);
+    let harvest = ix(
+        accounts::Harvest {
+            user,
+            config: harness.config,
+            reward_mint,
+            farm,
+            position,
+            reward_vault,
+            user_reward,
+            token_program: spl_token::ID,
+            associated_token_program: anchor_spl::associated_token::ID,
+            system_program: anchor_lang::system_program::ID,
+        }
+        .to_account_metas(None),
+        instruction::Harvest {}.data(),
+    );
+
+    let init_farm = ix(
+        accounts::InitializeFarm {
+            authority: user,
+            config: harness.config,
+            mint_lp: harness.mint_lp,
+            reward_mint,
+            farm,
+            farm_lp,
+            reward_vault,
+            token_program: spl_token::ID,
+            lp_token_program: spl_token_2022::ID,
+            associated_token_program: anchor_spl::associated_token::ID,
+            system_program: anchor_lang::system_program::ID,
+        }
+        .to_account_metas(None),
+        instruction::InitializeFarm {
+            reward_rate: REWARD_RATE,
+        }
+        .data(),
+    );
+    // Enough for 5 seconds of emissions
+    let fund_farm = ix(
+        accounts::FundFarm {
+            authority: user,
+            config: harness.config,
+            reward_mint,
+            farm,
+            reward_vault,
+            authority_reward: user_reward,
+            token_program: spl_token::ID,
+            associated_token_program: anchor_spl::associated_token::ID,
+            system_program: anchor_lang::system_program::ID,
+        }
+        .to_account_metas(None),
+        instruction::FundFarm {
+            amount: 5 * REWARD_RATE,
+        }
+        .data(),
+    );
+    let stake = ix(
+        stake_accounts.clone(),
+        instruction::StakeLp { amount: STAKE }.data(),
+    );
+    assert!(harness.send(&[init_farm, fund_farm, stake], &[]));
+
+    let staked: StakePosition = harness.fetch(&position);
+    assert_eq!(staked.amount, STAKE);
+    assert_eq!(staked.reward_debt, 0);
+    assert_eq!(harness.fetch::<Farm>(&farm).total_staked, STAKE);
+    assert_eq!(harness.token_amount(&farm_lp), STAKE);
+
+    // The only staker earns the whole emission
+    let reward_before = harness.token_amount(&user_reward);
+    harness.warp(3);
+    assert!(harness.send(&[harvest.clone()], &[]));
+    assert_eq!(
+        harness.token_amount(&user_reward) - reward_before,
+        3 * REWARD_RATE
+    );
+    let harvested: StakePosition = harness.fetch(&position);
+    assert_eq!(harvested.reward_debt, 3 * REWARD_RATE as u128);
+    assert_eq!(harvested.pending_rewards, 0);
+
+    // Nothing accrued since the last harvest
+    assert!(!harness.send(&[harvest.clone()], &[]));
+
+    // Only 2 seconds' worth is left in the vault, the rest stays owed
+    let reward_before = harness.token_amount(&user_reward);
+    harness.warp(10);
+    assert!(harness.send(&[harvest], &[]));
+    assert_eq!(
+        harness.token_amount(&user_reward) - reward_before,
+        2 * REWARD_RATE
+    );
+    assert_eq!(harness.token_amount(&reward_vault), 0);
+    let underfunded: StakePosition = harness.fetch(&position);
+    assert_eq!(underfunded.reward_debt, 13 * REWARD_RATE as u128);
+    assert_eq!(underfunded.pending_rewards, 8 * REWARD_RATE);
+
+    let lp_before = harness.token_amount(&user_lp);
+    let unstake = ix(
+        stake_accounts,
+        instruction::UnstakeLp { amount: STAKE }.data(),
+    );
+    assert!(harness.send(&[unstake], &[]));
+    assert_eq!(harness.token_amount(&user_lp) - lp_before, STAKE);
+    let unstaked: StakePosition = harness.fetch(&position);
+    assert_eq!(unstaked.amount, 0);
+    assert_eq!(unstaked.reward_debt, 0);
+    assert_eq!(unstaked.pending_rewards, 8 * REWARD_RATE);
+    assert_eq!(harness.fetch::<Farm>(&farm).total_staked, 0);
+}
+
+#[test]
+fn dynamic_fee_rises_after_a_price_move_and_decays_back() {
+    let dynamic_fee = DynamicFee {
+        min_fee: 30,
+        max_fee: 1_000,
+        volatility_factor: 10_000,
+        decay_period: 600,
+    };
+    let mut harness = Harness::new(10, 30);
+    let liquidity = harness.liquidity_accounts();
+    let seed_deposit = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: liquidity.to_account_metas(None),
+        data: instruction::Deposit {
+            amount: 1_000_000_000,
+            max_x: 1_000_000_000,
+            max_y: 1_000_000_000,
+        }
+        .data(),
+    };
+    assert!(harness.send(&[seed_deposit], &[]));
+    assert!(harness.set_dynamic_fee(Some(dynamic_fee)));
+
+    // Executes a swap of Y for X and checks it paid out what amm-math quoted at `fee`
+    let swap_y = |harness: &mut Harness, fee: u16| {
+        let pool = harness.pool();
+        let quote = pool
+            .quote_swap(false, 1_000_000, 0, harness.now(), None)
+            .unwrap();
+        assert_eq!(quote.fee, fee);
+        let x_before = harness.token_amount(&liquidity.user_x);
+        let op = Op::Swap {
+            is_x: false,
+            amount: 1_000_000,
+        };
+        assert!(harness.execute(op, &pool));
+        assert_eq!(
+            harness.token_amount(&liquidity.user_x) - x_before,
+            quote.amount_out
+        );
+    };
+
+    // The first swap only sets the reference price, so a calm pool charges the floor
+    swap_y(&mut harness, dynamic_fee.min_fee);
+
+    // Selling 5% of the X reserve moves the price by about 9%, i.e. ~900 bps of volatility
+    let move_price = Op::Swap {
+        is_x: true,
+        amount: 50_000_000,
+    };
+    let pool = harness.pool();
+    assert!(harness.execute(move_price, &pool));
+    let volatile = harness.pool().config.swap_fee(harness.now());
+    assert!(volatile > dynamic_fee.min_fee + 800 && volatile < dynamic_fee.max_fee);
+    swap_y(&mut harness, volatile);
+
+    // Halfway through the decay period the surcharge has halved
+    harness.warp(300);
+    let half_decayed = harness.pool().config.swap_fee(harness.now());
+    assert!(dynamic_fee.min_fee < half_decayed && half_decayed < volatile);
+
+    // Once the decay period passes without a swap the fee is back at the floor
+    harness.warp(300);
+    swap_y(&mut harness, dynamic_fee.min_fee);
+}
@@ -0,0 +1,125 @@
+//! Fixed examples for the quoting math, with the expected values worked out by hand
+//! from the program's rounding rules. The LiteSVM tests check that the program
+//! agrees with amm-math, these pin down what both of them produce.
+
+mod common;
+
+use anchor_amm_q4_25::state::DynamicFee;
+use common::empty_pool;
+
+const DYNAMIC_FEE: DynamicFee = DynamicFee {
+    min_fee: 30,
+    max_fee: 500,
+    volatility_factor: 10_000,
+    decay_period: 600,
+};
+
+#[test]
+fn first_deposit_takes_the_max_amounts() {
+    let mut pool = empty_pool(30);
+    let quote = pool.deposit(100_000_000, 100_000_000, 40_000_000).unwrap();
+
+    assert_eq!(
+        (quote.lp, quote.x, quote.y),
+        (100_000_000, 100_000_000, 40_000_000)
+    );
+    assert_eq!(
+        (pool.config.reserve_x, pool.config.reserve_y),
+        (100_000_000, 40_000_000)
+    );
+    assert_eq!(
+        (pool.vault_x, pool.vault_y, pool.lp_supply),
+        (100_000_000, 40_000_000, 100_000_000)
+    );
+}
+
+#[test]
+fn deposits_round_up_and_withdrawals_round_down() {
+    let mut pool = empty_pool(30);
+    pool.deposit(7, 1_000, 3).unwrap();
+
+    // 2/7 of the reserves is 285.7 X and 0.86 Y
+    let deposit = pool.quote_deposit(2, u64::MAX, u64::MAX).unwrap();
+    assert_eq!((deposit.x, deposit.y), (286, 1));
+    let withdraw = pool.quote_withdraw(2, 0, 0).unwrap();
+    assert_eq!((withdraw.x, withdraw.y), (285, 0));
+
+    assert!(pool.quote_deposit(2, 285, 1).is_err());
+    assert!(pool.quote_withdraw(2, 286, 0).is_err());
+}
+
+#[test]
+fn fee_free_swap_follows_the_curve() {
+    let mut pool = empty_pool(0);
+    pool.deposit(1_000_000, 1_000_000, 1_000_000).unwrap();
+
+    // k / (x + dx) = 10^12 / 2 * 10^6 leaves half of Y
+    let quote = pool.swap(true, 1_000_000, 500_000, 0, None).unwrap();
+    assert_eq!(
+        (quote.amount_in, quote.amount_out, quote.fee, quote.referral),
+        (1_000_000, 500_000, 0, 0)
+    );
+    assert_eq!(
+        (pool.config.reserve_x, pool.config.reserve_y),
+        (2_000_000, 500_000)
+    );
+    assert_eq!((pool.vault_x, pool.vault_y), (2_000_000, 500_000));
+}
+
+#[test]
+fn swap_output_is_rounded_in_the_pools_favour() {
+    let mut pool = empty_pool(0);
+    pool.deposit(1_000, 1_000, 1_000).unwrap();
+
+    // 10^6 / 1003 = 997.01 of Y must stay, so only 2 of the 2.99 can go out
+    assert_eq!(pool.config.max_swap_out(true, 3).unwrap(), 2);
+    let quote = pool.quote_swap(true, 3, 0, 0, None).unwrap();
+    assert_eq!((quote.amount_in, quote.amount_out), (3, 2));
+    assert!(pool.quote_swap(true, 3, 3, 0, None).is_err());
+}
+
+#[test]
+fn fee_is_kept_by_the_pool() {
+    let mut free = empty_pool(0);
+    free.deposit(1_000_000, 1_000_000, 1_000_000).unwrap();
+    let mut charged = empty_pool(30);
+    charged.deposit(1_000_000, 1_000_000, 1_000_000).unwrap();
+
+    let free_quote = free.quote_swap(true, 1_000_000, 0, 0, None).unwrap();
+    let quote = charged.swap(true, 1_000_000, 0, 0, None).unwrap();
+    assert_eq!(quote.fee, 30);
+    assert_eq!(quote.amount_in, 1_000_000);
+    assert!(quote.amount_out < free_quote.amount_out);
+    assert!(
+        charged.config.reserve_x as u128 * charged.config.reserve_y as u128 > 1_000_000_000_000
+    );
+}
+
+#[test]
+fn dynamic_fee_tracks_and_decays_volatility() {
+    let mut pool = empty_pool(30);
+    pool.config.dynamic_fee = Some(DYNAMIC_FEE);
+
+    // The first price only becomes the reference, a 10% move then counts 1_000 bps
+    pool.config.record_price(1_000, 1_000, 0);
+    assert_eq!(
+        (pool.config.volatility, pool.config.last_price),
+        (0, 1_000_000_000_000)
+    );
+    assert_eq!(pool.config.swap_fee(0), 30);
+    pool.config.record_price(1_000, 1_100, 0);
+    assert_eq!(
+        (pool.config.volatility, pool.config.last_price),
+        (1_000, 1_100_000_000_000)
+    );
+
+    // 30 + 1_000 is capped at the max, then decays linearly over the period
+    assert_eq!(pool.config.swap_fee(0), 500);
+    assert_eq!(pool.config.decayed_volatility(300), 500);
+    assert_eq!(pool.config.swap_fee(450), 280);
+    assert_eq!(pool.config.swap_fee(600), 30);
+
+    // Without a dynamic fee the flat fee applies whatever the volatility
+    pool.config.dynamic_fee = None;
+    assert_eq!(pool.config.swap_fee(0), 30);
+}
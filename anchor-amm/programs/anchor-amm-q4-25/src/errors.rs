@@ -39,6 +39,14 @@ pub enum AmmError {
     InsufficientBalance,
     #[msg("Zero balance.")]
     ZeroBalance,
+    #[msg("No rewards available to harvest.")]
+    NothingToHarvest,
+    #[msg("Pool still has outstanding LP tokens.")]
+    PoolNotEmpty,
+    #[msg("Invalid referrer.")]
+    InvalidReferrer,
+    #[msg("Invalid order accounts.")]
+    InvalidOrder,
 }
 
 impl From<CurveError> for AmmError {
@@ -0,0 +1,90 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token::{close_account, transfer, CloseAccount, Mint, Token, TokenAccount, Transfer},
+};
+
+use crate::{
+    errors::AmmError,
+    state::{Config, Order},
+};
+
+#[derive(Accounts)]
+pub struct CancelOrder<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        constraint = mint_in.key() == order.mint_in(&config) @ AmmError::InvalidToken,
+    )]
+    pub mint_in: Account<'info, Mint>,
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        has_one = config,
+        seeds = [b"order", config.key().as_ref(), owner.key().as_ref(), order.seed.to_le_bytes().as_ref()],
+        bump = order.bump,
+    )]
+    pub order: Account<'info, Order>,
+    #[account(
+        mut,
+        associated_token::mint = mint_in,
+        associated_token::authority = order,
+    )]
+    pub order_vault: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = mint_in,
+        associated_token::authority = owner,
+    )]
+    pub owner_in: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CancelOrder<'info> {
+    // Returns the escrowed tokens; rent and the unused crank fee go back through `close`
+    pub fn cancel_order(&mut self) -> Result<()> {
+        let config_key = self.config.key();
+        let owner_key = self.owner.key();
+        let seed = self.order.seed.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"order",
+            config_key.as_ref(),
+            owner_key.as_ref(),
+            seed.as_ref(),
+            &[self.order.bump],
+        ]];
+
+        let cpi_accounts = Transfer {
+            from: self.order_vault.to_account_info(),
+            to: self.owner_in.to_account_info(),
+            authority: self.order.to_account_info(),
+        };
+        let ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        transfer(ctx, self.order_vault.amount)?;
+
+        let cpi_accounts = CloseAccount {
+            account: self.order_vault.to_account_info(),
+            destination: self.owner.to_account_info(),
+            authority: self.order.to_account_info(),
+        };
+        let ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        close_account(ctx)
+    }
+}
@@ -0,0 +1,138 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token::{close_account, transfer, CloseAccount, Mint, Token, TokenAccount, Transfer},
+    token_2022::Token2022,
+    token_interface,
+};
+
+use crate::{errors::AmmError, state::Config};
+
+// Pools with an authority are closed by it. An empty pool without one has nobody to
+// answer to, so anyone may close it and keep the rent. The LP mint's token metadata
+// cannot be closed: its rent stays put and `initialize` reuses it if the seed comes back
+#[derive(Accounts)]
+pub struct ClosePool<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub mint_x: Account<'info, Mint>,
+    pub mint_y: Account<'info, Mint>,
+    #[account(
+        mut,
+        close = authority,
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+        constraint = config.authority.is_none() || config.authority == Some(authority.key())
+            @ AmmError::InvalidAuthority,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [b"lp", config.key().as_ref()],
+        bump = config.lp_bump,
+    )]
+    pub mint_lp: InterfaceAccount<'info, token_interface::Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+    )]
+    pub vault_x: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+    )]
+    pub vault_y: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = mint_x,
+        associated_token::authority = authority,
+    )]
+    pub authority_x: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = mint_y,
+        associated_token::authority = authority,
+    )]
+    pub authority_y: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub lp_token_program: Program<'info, Token2022>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ClosePool<'info> {
+    pub fn close_pool(&mut self) -> Result<()> {
+        require!(self.mint_lp.supply == 0, AmmError::PoolNotEmpty);
+
+        // Anything left in the vaults is dust or donations nobody has a claim on
+        self.drain_and_close_vault(true)?;
+        self.drain_and_close_vault(false)?;
+        self.close_lp_mint()
+    }
+
+    // The LP mint carries a close authority, so its rent comes back along with the config's
+    pub fn close_lp_mint(&self) -> Result<()> {
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"config",
+            &self.config.seed.to_le_bytes(),
+            &[self.config.config_bump],
+        ]];
+
+        let cpi_accounts = token_interface::CloseAccount {
+            account: self.mint_lp.to_account_info(),
+            destination: self.authority.to_account_info(),
+            authority: self.config.to_account_info(),
+        };
+        let ctx = CpiContext::new_with_signer(
+            self.lp_token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        token_interface::close_account(ctx)
+    }
+
+    pub fn drain_and_close_vault(&self, is_x: bool) -> Result<()> {
+        let (vault, to) = match is_x {
+            true => (&self.vault_x, &self.authority_x),
+            false => (&self.vault_y, &self.authority_y),
+        };
+
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"config",
+            &self.config.seed.to_le_bytes(),
+            &[self.config.config_bump],
+        ]];
+
+        if vault.amount > 0 {
+            let cpi_accounts = Transfer {
+                from: vault.to_account_info(),
+                to: to.to_account_info(),
+                authority: self.config.to_account_info(),
+            };
+            let ctx = CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                cpi_accounts,
+                signer_seeds,
+            );
+            transfer(ctx, vault.amount)?;
+        }
+
+        let cpi_accounts = CloseAccount {
+            account: vault.to_account_info(),
+            destination: self.authority.to_account_info(),
+            authority: self.config.to_account_info(),
+        };
+        let ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        close_account(ctx)
+    }
+}
@@ -1,11 +1,12 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{mint_to, transfer, Mint, MintTo, Token, TokenAccount, Transfer},
+    token::{transfer, Mint, Token, TokenAccount, Transfer},
+    token_2022::Token2022,
+    token_interface::{self, mint_to, MintTo},
 };
-use constant_product_curve::ConstantProduct;
 
-use crate::{errors::AmmError, state::Config};
+use crate::{errors::AmmError, state::Config, wsol};
 
 #[derive(Accounts)]
 pub struct Deposit<'info> {
@@ -14,6 +15,7 @@ pub struct Deposit<'info> {
     pub mint_x: Account<'info, Mint>,
     pub mint_y: Account<'info, Mint>,
     #[account(
+        mut,
         has_one = mint_x,
         has_one = mint_y,
         seeds = [b"config", config.seed.to_le_bytes().as_ref()],
@@ -25,7 +27,7 @@ pub struct Deposit<'info> {
         seeds = [b"lp", config.key().as_ref()],
         bump = config.lp_bump,
     )]
-    pub mint_lp: Account<'info, Mint>,
+    pub mint_lp: InterfaceAccount<'info, token_interface::Mint>,
     #[account(
         mut,
         associated_token::mint = mint_x,
@@ -39,13 +41,15 @@ pub struct Deposit<'info> {
     )]
     pub vault_y: Account<'info, TokenAccount>,
     #[account(
-        mut,
+        init_if_needed,
+        payer = user,
         associated_token::mint = mint_x,
         associated_token::authority = user,
     )]
     pub user_x: Account<'info, TokenAccount>,
     #[account(
-        mut,
+        init_if_needed,
+        payer = user,
         associated_token::mint = mint_y,
         associated_token::authority = user,
     )]
@@ -55,9 +59,11 @@ pub struct Deposit<'info> {
         payer = user,
         associated_token::mint = mint_lp,
         associated_token::authority = user,
+        associated_token::token_program = lp_token_program,
     )]
-    pub user_lp: Account<'info, TokenAccount>,
+    pub user_lp: InterfaceAccount<'info, token_interface::TokenAccount>,
     pub token_program: Program<'info, Token>,
+    pub lp_token_program: Program<'info, Token2022>,
     pub system_program: Program<'info, System>,
     pub associated_token_program: Program<'info, AssociatedToken>,
 }
@@ -69,35 +75,59 @@ impl<'info> Deposit<'info> {
         max_x: u64,  // Maximum amount of token X that the user is willing to deposit
         max_y: u64,  // Maximum amount of token Y that the user is willing to deposit
     ) -> Result<()> {
-        require!(self.config.locked == false, AmmError::PoolLocked);
+        require!(!self.config.locked, AmmError::PoolLocked);
         require!(amount != 0, AmmError::InvalidAmount);
 
         let (x, y) = match self.mint_lp.supply == 0
-            && self.vault_x.amount == 0
-            && self.vault_y.amount == 0
+            && self.config.reserve_x == 0
+            && self.config.reserve_y == 0
         {
             true => (max_x, max_y),
-            false => {
-                let amounts = ConstantProduct::xy_deposit_amounts_from_l(
-                    self.vault_x.amount,
-                    self.vault_y.amount,
-                    self.mint_lp.supply,
-                    amount,
-                    6,
-                )
-                .unwrap();
-                (amounts.x, amounts.y)
-            }
+            false => self.config.deposit_amounts(self.mint_lp.supply, amount)?,
         };
 
         require!(x <= max_x && y <= max_y, AmmError::SlippageExceeded);
 
+        let temporary_x = wsol::is_temporary(&self.mint_x, &self.user_x);
+        let temporary_y = wsol::is_temporary(&self.mint_y, &self.user_y);
+        // wrap native SOL if either side is the WSOL mint
+        self.wrap_sol(true, x)?;
+        self.wrap_sol(false, y)?;
         // deposit token x
         self.deposit_tokens(true, x)?;
         // deposit token y
         self.deposit_tokens(false, y)?;
         // mint lp tokens
-        self.mint_lp_tokens(amount)
+        self.mint_lp_tokens(amount)?;
+        // track the new reserves
+        self.config.reserve_x = self
+            .config
+            .reserve_x
+            .checked_add(x)
+            .ok_or(AmmError::Overflow)?;
+        self.config.reserve_y = self
+            .config
+            .reserve_y
+            .checked_add(y)
+            .ok_or(AmmError::Overflow)?;
+        // return leftover WSOL as native SOL
+        wsol::unwrap_sol(&self.user_x, &self.user, &self.token_program, temporary_x)?;
+        wsol::unwrap_sol(&self.user_y, &self.user, &self.token_program, temporary_y)
+    }
+
+    pub fn wrap_sol(&self, is_x: bool, amount: u64) -> Result<()> {
+        let (mint, user_ata) = match is_x {
+            true => (&self.mint_x, &self.user_x),
+            false => (&self.mint_y, &self.user_y),
+        };
+        wsol::wrap_sol(
+            mint,
+            user_ata,
+            &self.user,
+            &self.system_program,
+            &self.token_program,
+            amount,
+        )
     }
 
     pub fn deposit_tokens(&self, is_x: bool, amount: u64) -> Result<()> {
@@ -126,7 +156,7 @@ impl<'info> Deposit<'info> {
     }
 
     pub fn mint_lp_tokens(&self, amount: u64) -> Result<()> {
-        let cpi_program = self.token_program.to_account_info();
+        let cpi_program = self.lp_token_program.to_account_info();
 
         let cpi_accounts = MintTo {
             mint: self.mint_lp.to_account_info(),
@@ -0,0 +1,228 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{close_account, transfer, CloseAccount, Token, TokenAccount, Transfer},
+    token_interface,
+};
+use constant_product_curve::{ConstantProduct, CurveError, LiquidityPair};
+
+use crate::{
+    errors::AmmError,
+    state::{Config, Order},
+};
+
+// Accounts passed per order in `remaining_accounts`: order, order vault, owner, owner's output token account
+const ACCOUNTS_PER_ORDER: usize = 4;
+
+#[derive(Accounts)]
+pub struct FillOrders<'info> {
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        seeds = [b"lp", config.key().as_ref()],
+        bump = config.lp_bump,
+    )]
+    pub mint_lp: InterfaceAccount<'info, token_interface::Mint>,
+    #[account(
+        mut,
+        associated_token::mint = config.mint_x,
+        associated_token::authority = config,
+    )]
+    pub vault_x: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = config.mint_y,
+        associated_token::authority = config,
+    )]
+    pub vault_y: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> FillOrders<'info> {
+    // Fills every order whose limit price the pool can meet right now and skips the rest,
+    // so a crank can pass a whole batch without knowing in advance which ones cross
+    pub fn fill_orders(&mut self, remaining_accounts: &'info [AccountInfo<'info>]) -> Result<()> {
+        require!(!self.config.locked, AmmError::PoolLocked);
+        require!(
+            remaining_accounts.len() % ACCOUNTS_PER_ORDER == 0,
+            AmmError::InvalidOrder
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        for accounts in remaining_accounts.chunks_exact(ACCOUNTS_PER_ORDER) {
+            let [order, order_vault, owner, owner_out] = accounts else {
+                unreachable!();
+            };
+            let order_account = Account::<Order>::try_from(order)?;
+            let order_vault_account = Account::<TokenAccount>::try_from(order_vault)?;
+            let owner_out_account = Account::<TokenAccount>::try_from(owner_out)?;
+
+            require_keys_eq!(
+                order_account.config,
+                self.config.key(),
+                AmmError::InvalidOrder
+            );
+            require_keys_eq!(order_account.owner, owner.key(), AmmError::InvalidOrder);
+            require_keys_eq!(
+                order_vault_account.owner,
+                order.key(),
+                AmmError::InvalidOrder
+            );
+            require_keys_eq!(
+                order_vault_account.mint,
+                order_account.mint_in(&self.config),
+                AmmError::InvalidToken
+            );
+            require_keys_eq!(owner_out_account.owner, owner.key(), AmmError::InvalidOrder);
+            require_keys_eq!(
+                owner_out_account.mint,
+                order_account.mint_out(&self.config),
+                AmmError::InvalidToken
+            );
+
+            let Some(amount_out) = self.quote(&order_account, now)? else {
+                continue;
+            };
+            self.fill(
+                order_account,
+                order_vault,
+                owner,
+                owner_out,
+                amount_out,
+                now,
+            )?;
+        }
+        Ok(())
+    }
+
+    // Output the pool pays for the whole order, or None while the price has not crossed
+    pub fn quote(&self, order: &Order, now: i64) -> Result<Option<u64>> {
+        let mut curve = ConstantProduct::init(
+            self.config.reserve_x,
+            self.config.reserve_y,
+            self.mint_lp.supply,
+            self.config.swap_fee(now),
+            None,
+        )
+        .map_err(AmmError::from)?;
+        let pair = match order.is_x {
+            true => LiquidityPair::X,
+            false => LiquidityPair::Y,
+        };
+        let swaps = match curve.swap(pair, order.amount_in, order.min_amount_out) {
+            Ok(swaps) => swaps,
+            // The pool can't meet the limit price yet, the order keeps resting
+            Err(CurveError::SlippageLimitExceeded) => return Ok(None),
+            Err(error) => return Err(AmmError::from(error).into()),
+        };
+        let amount_out = swaps
+            .withdraw
+            .min(self.config.max_swap_out(order.is_x, order.amount_in)?);
+
+        Ok((amount_out != 0 && amount_out >= order.min_amount_out).then_some(amount_out))
+    }
+
+    pub fn fill(
+        &mut self,
+        order: Account<'info, Order>,
+        order_vault: &'info AccountInfo<'info>,
+        owner: &'info AccountInfo<'info>,
+        owner_out: &'info AccountInfo<'info>,
+        amount_out: u64,
+        now: i64,
+    ) -> Result<()> {
+        let config_key = self.config.key();
+        let seed = order.seed.to_le_bytes();
+        let order_seeds: &[&[&[u8]]] = &[&[
+            b"order",
+            config_key.as_ref(),
+            order.owner.as_ref(),
+            seed.as_ref(),
+            &[order.bump],
+        ]];
+        let config_seed = self.config.seed.to_le_bytes();
+        let config_seeds: &[&[&[u8]]] =
+            &[&[b"config", config_seed.as_ref(), &[self.config.config_bump]]];
+
+        let (vault_in, vault_out) = match order.is_x {
+            true => (&self.vault_x, &self.vault_y),
+            false => (&self.vault_y, &self.vault_x),
+        };
+
+        // order vault -> pool. The whole balance moves so a donation to the order vault can't
+        // keep it from closing; anything above amount_in lands outside the reserves, for skim
+        let vault_balance = Account::<TokenAccount>::try_from(order_vault)?.amount;
+        let cpi_accounts = Transfer {
+            from: order_vault.to_account_info(),
+            to: vault_in.to_account_info(),
+            authority: order.to_account_info(),
+        };
+        let ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            order_seeds,
+        );
+        transfer(ctx, vault_balance)?;
+
+        // pool -> order owner
+        let cpi_accounts = Transfer {
+            from: vault_out.to_account_info(),
+            to: owner_out.to_account_info(),
+            authority: self.config.to_account_info(),
+        };
+        let ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            config_seeds,
+        );
+        transfer(ctx, amount_out)?;
+
+        let cpi_accounts = CloseAccount {
+            account: order_vault.to_account_info(),
+            destination: owner.to_account_info(),
+            authority: order.to_account_info(),
+        };
+        let ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            order_seeds,
+        );
+        close_account(ctx)?;
+
+        let (x, y) = match order.is_x {
+            true => (
+                self.config
+                    .reserve_x
+                    .checked_add(order.amount_in)
+                    .ok_or(AmmError::Overflow)?,
+                self.config
+                    .reserve_y
+                    .checked_sub(amount_out)
+                    .ok_or(AmmError::Underflow)?,
+            ),
+            false => (
+                self.config
+                    .reserve_x
+                    .checked_sub(amount_out)
+                    .ok_or(AmmError::Underflow)?,
+                self.config
+                    .reserve_y
+                    .checked_add(order.amount_in)
+                    .ok_or(AmmError::Overflow)?,
+            ),
+        };
+        self.config.reserve_x = x;
+        self.config.reserve_y = y;
+        self.config.record_price(x, y, now);
+
+        // Crank fee to the filler, rent back to the owner
+        order.sub_lamports(order.crank_fee)?;
+        self.cranker.add_lamports(order.crank_fee)?;
+        order.close(owner.to_account_info())
+    }
+}
@@ -0,0 +1,94 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token::{transfer, Mint, Token, TokenAccount, Transfer},
+};
+
+use crate::{
+    errors::AmmError,
+    state::{Config, Farm, StakePosition},
+};
+
+#[derive(Accounts)]
+pub struct Harvest<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+    pub reward_mint: Account<'info, Mint>,
+    #[account(
+        mut,
+        has_one = config,
+        has_one = reward_mint,
+        seeds = [b"farm", config.key().as_ref(), reward_mint.key().as_ref()],
+        bump = farm.bump,
+    )]
+    pub farm: Account<'info, Farm>,
+    #[account(
+        mut,
+        has_one = farm,
+        constraint = position.owner == user.key() @ AmmError::InvalidAuthority,
+        seeds = [b"position", farm.key().as_ref(), user.key().as_ref()],
+        bump = position.bump,
+    )]
+    pub position: Account<'info, StakePosition>,
+    #[account(
+        mut,
+        associated_token::mint = reward_mint,
+        associated_token::authority = farm,
+    )]
+    pub reward_vault: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = reward_mint,
+        associated_token::authority = user,
+    )]
+    pub user_reward: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> Harvest<'info> {
+    pub fn harvest(&mut self) -> Result<()> {
+        self.farm.update_rewards(Clock::get()?.unix_timestamp)?;
+        self.position.settle(&self.farm)?;
+        self.position.reward_debt = self.farm.reward_debt(self.position.amount)?;
+
+        // If the farm is underfunded, pay what is there and keep the rest owed
+        let payout = self.position.pending_rewards.min(self.reward_vault.amount);
+        require!(payout > 0, AmmError::NothingToHarvest);
+
+        self.withdraw_rewards(payout)?;
+        self.position.pending_rewards -= payout;
+
+        Ok(())
+    }
+
+    pub fn withdraw_rewards(&self, amount: u64) -> Result<()> {
+        let cpi_accounts = Transfer {
+            from: self.reward_vault.to_account_info(),
+            to: self.user_reward.to_account_info(),
+            authority: self.farm.to_account_info(),
+        };
+
+        let config_key = self.config.key();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"farm",
+            config_key.as_ref(),
+            self.farm.reward_mint.as_ref(),
+            &[self.farm.bump],
+        ]];
+
+        let ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        transfer(ctx, amount)
+    }
+}
@@ -1,7 +1,15 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
+    metadata::{
+        create_metadata_accounts_v3,
+        mpl_token_metadata::{types::DataV2, MAX_NAME_LENGTH, MAX_SYMBOL_LENGTH},
+        update_metadata_accounts_v2, CreateMetadataAccountsV3, Metadata, MetadataAccount,
+        UpdateMetadataAccountsV2,
+    },
     token::{Mint, Token, TokenAccount},
+    token_2022::Token2022,
+    token_interface,
 };
 
 use crate::state::Config;
@@ -20,8 +28,10 @@ pub struct Initialize<'info> {
         bump,
         mint::decimals = 6,
         mint::authority = config,
+        mint::token_program = lp_token_program,
+        extensions::close_authority::authority = config,
     )]
-    pub mint_lp: Account<'info, Mint>,
+    pub mint_lp: InterfaceAccount<'info, token_interface::Mint>,
     #[account(
         init,
         payer = initializer,
@@ -44,9 +54,34 @@ pub struct Initialize<'info> {
         space = Config::DISCRIMINATOR.len() + Config::INIT_SPACE,
     )]
     pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [b"metadata", metadata_program.key().as_ref(), mint_lp.key().as_ref()],
+        bump,
+        seeds::program = metadata_program.key(),
+    )]
+    /// CHECK: Created by the token metadata program
+    pub lp_metadata: UncheckedAccount<'info>,
+    // Optional metadata of the pair, used to name the LP token
+    #[account(
+        seeds = [b"metadata", metadata_program.key().as_ref(), mint_x.key().as_ref()],
+        bump,
+        seeds::program = metadata_program.key(),
+    )]
+    pub metadata_x: Option<Account<'info, MetadataAccount>>,
+    #[account(
+        seeds = [b"metadata", metadata_program.key().as_ref(), mint_y.key().as_ref()],
+        bump,
+        seeds::program = metadata_program.key(),
+    )]
+    pub metadata_y: Option<Account<'info, MetadataAccount>>,
     pub token_program: Program<'info, Token>,
+    // LP mints live under Token-2022 so close_pool can reclaim their rent
+    pub lp_token_program: Program<'info, Token2022>,
     pub associated_token_program: Program<'info, AssociatedToken>,
+    pub metadata_program: Program<'info, Metadata>,
     pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
 }
 
 impl<'info> Initialize<'info> {
@@ -63,6 +98,12 @@ impl<'info> Initialize<'info> {
             mint_x: self.mint_x.key(),
             mint_y: self.mint_y.key(),
             fee,
+            reserve_x: 0,
+            reserve_y: 0,
+            dynamic_fee: None,
+            volatility: 0,
+            last_price: 0,
+            last_swap: 0,
             locked: false,
             config_bump: bumps.config,
             lp_bump: bumps.mint_lp,
@@ -70,4 +111,79 @@ impl<'info> Initialize<'info> {
 
         Ok(())
     }
+
+    pub fn create_lp_metadata(&self) -> Result<()> {
+        let symbol_x = Self::pair_symbol(&self.metadata_x, &self.mint_x.key());
+        let symbol_y = Self::pair_symbol(&self.metadata_y, &self.mint_y.key());
+
+        let mut name = format!("{}-{} LP", symbol_x, symbol_y);
+        name.truncate(MAX_NAME_LENGTH);
+        let mut symbol = format!("{}{}LP", symbol_x, symbol_y);
+        symbol.truncate(MAX_SYMBOL_LENGTH);
+
+        let data = DataV2 {
+            name,
+            symbol,
+            uri: String::new(),
+            seller_fee_basis_points: 0,
+            creators: None,
+            collection: None,
+            uses: None,
+        };
+
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"config",
+            &self.config.seed.to_le_bytes(),
+            &[self.config.config_bump],
+        ]];
+
+        // Token metadata cannot be closed, so a pool closed and re-created with the same
+        // seed finds its old metadata at the LP mint's address, still owned by this config
+        if !self.lp_metadata.data_is_empty() {
+            let cpi_accounts = UpdateMetadataAccountsV2 {
+                metadata: self.lp_metadata.to_account_info(),
+                update_authority: self.config.to_account_info(),
+            };
+            let ctx = CpiContext::new_with_signer(
+                self.metadata_program.to_account_info(),
+                cpi_accounts,
+                signer_seeds,
+            );
+            return update_metadata_accounts_v2(ctx, None, Some(data), None, None);
+        }
+
+        let cpi_accounts = CreateMetadataAccountsV3 {
+            metadata: self.lp_metadata.to_account_info(),
+            mint: self.mint_lp.to_account_info(),
+            mint_authority: self.config.to_account_info(),
+            payer: self.initializer.to_account_info(),
+            update_authority: self.config.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+            rent: self.rent.to_account_info(),
+        };
+
+        let ctx = CpiContext::new_with_signer(
+            self.metadata_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        create_metadata_accounts_v3(ctx, data, true, true, None)
+    }
+
+    // Symbol from the mint's metadata, or the start of its address when it has none.
+    // Symbols are ASCII by convention, so keep to that to make truncation safe.
+    fn pair_symbol(metadata: &Option<Account<'info, MetadataAccount>>, mint: &Pubkey) -> String {
+        let symbol: String = metadata
+            .as_ref()
+            .map(|metadata| metadata.symbol.trim_matches(char::from(0)).trim().to_string())
+            .unwrap_or_default()
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect();
+        match symbol.is_empty() {
+            true => mint.to_string()[..4].to_string(),
+            false => symbol,
+        }
+    }
 }
@@ -1,9 +1,37 @@
+pub mod cancel_order;
+pub mod close_pool;
 pub mod deposit;
+pub mod fill_orders;
+pub mod fund_farm;
+pub mod harvest;
 pub mod initialize;
+pub mod initialize_farm;
+pub mod place_order;
+pub mod set_dynamic_fee;
+pub mod set_partner;
+pub mod set_rate;
+pub mod skim;
+pub mod stake_lp;
 pub mod swap;
+pub mod sync;
+pub mod unstake_lp;
 pub mod withdraw;
 
+pub use cancel_order::*;
+pub use close_pool::*;
 pub use deposit::*;
+pub use fill_orders::*;
+pub use fund_farm::*;
+pub use harvest::*;
 pub use initialize::*;
+pub use initialize_farm::*;
+pub use place_order::*;
+pub use set_dynamic_fee::*;
+pub use set_partner::*;
+pub use set_rate::*;
+pub use skim::*;
+pub use stake_lp::*;
 pub use swap::*;
+pub use sync::*;
+pub use unstake_lp::*;
 pub use withdraw::*;
@@ -0,0 +1,107 @@
+use anchor_lang::{prelude::*, system_program};
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token::{transfer, Mint, Token, TokenAccount, Transfer},
+};
+
+use crate::{
+    errors::AmmError,
+    state::{Config, Order},
+};
+
+#[derive(Accounts)]
+#[instruction(seed: u64, is_x: bool)]
+pub struct PlaceOrder<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        constraint = mint_in.key() == match is_x {
+            true => config.mint_x,
+            false => config.mint_y,
+        } @ AmmError::InvalidToken,
+    )]
+    pub mint_in: Account<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint_in,
+        associated_token::authority = owner,
+    )]
+    pub owner_in: Account<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = owner,
+        seeds = [b"order", config.key().as_ref(), owner.key().as_ref(), seed.to_le_bytes().as_ref()],
+        bump,
+        space = Order::DISCRIMINATOR.len() + Order::INIT_SPACE,
+    )]
+    pub order: Account<'info, Order>,
+    #[account(
+        init,
+        payer = owner,
+        associated_token::mint = mint_in,
+        associated_token::authority = order,
+    )]
+    pub order_vault: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> PlaceOrder<'info> {
+    pub fn place_order(
+        &mut self,
+        seed: u64,
+        is_x: bool,
+        amount_in: u64,      // Tokens to sell
+        min_amount_out: u64, // Tokens to receive for the whole amount_in
+        crank_fee: u64,      // Lamports paid to the cranker that fills the order
+        bumps: PlaceOrderBumps,
+    ) -> Result<()> {
+        require!(!self.config.locked, AmmError::PoolLocked);
+        require!(amount_in > 0 && min_amount_out > 0, AmmError::InvalidAmount);
+
+        self.order.set_inner(Order {
+            config: self.config.key(),
+            owner: self.owner.key(),
+            seed,
+            is_x,
+            amount_in,
+            min_amount_out,
+            crank_fee,
+            bump: bumps.order,
+        });
+
+        self.deposit_tokens(amount_in)?;
+        self.deposit_crank_fee(crank_fee)
+    }
+
+    pub fn deposit_tokens(&self, amount: u64) -> Result<()> {
+        let cpi_accounts = Transfer {
+            from: self.owner_in.to_account_info(),
+            to: self.order_vault.to_account_info(),
+            authority: self.owner.to_account_info(),
+        };
+
+        let ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+        transfer(ctx, amount)
+    }
+
+    // Held on the order account on top of its rent until the order is filled or cancelled
+    pub fn deposit_crank_fee(&self, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+
+        let cpi_accounts = system_program::Transfer {
+            from: self.owner.to_account_info(),
+            to: self.order.to_account_info(),
+        };
+        let ctx = CpiContext::new(self.system_program.to_account_info(), cpi_accounts);
+        system_program::transfer(ctx, amount)
+    }
+}
@@ -0,0 +1,30 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::AmmError,
+    state::{Config, DynamicFee},
+};
+
+#[derive(Accounts)]
+pub struct SetDynamicFee<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+        constraint = config.authority.is_some() @ AmmError::NoAuthoritySet,
+        constraint = config.authority == Some(authority.key()) @ AmmError::InvalidAuthority,
+    )]
+    pub config: Account<'info, Config>,
+}
+
+impl<'info> SetDynamicFee<'info> {
+    pub fn set_dynamic_fee(&mut self, dynamic_fee: Option<DynamicFee>) -> Result<()> {
+        if let Some(dynamic_fee) = dynamic_fee {
+            dynamic_fee.validate()?;
+        }
+        self.config.dynamic_fee = dynamic_fee;
+        self.config.volatility = 0;
+        Ok(())
+    }
+}
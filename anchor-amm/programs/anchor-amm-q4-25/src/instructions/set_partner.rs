@@ -0,0 +1,56 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::AmmError,
+    state::{Config, Partner},
+};
+
+#[derive(Accounts)]
+#[instruction(referrer: Pubkey)]
+pub struct SetPartner<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+        constraint = config.authority.is_some() @ AmmError::NoAuthoritySet,
+        constraint = config.authority == Some(authority.key()) @ AmmError::InvalidAuthority,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        seeds = [b"partner", config.key().as_ref(), referrer.as_ref()],
+        bump,
+        space = Partner::DISCRIMINATOR.len() + Partner::INIT_SPACE,
+    )]
+    pub partner: Account<'info, Partner>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> SetPartner<'info> {
+    pub fn set_partner(
+        &mut self,
+        referrer: Pubkey,
+        fee_share: u16, // Share of the swap fee paid to the referrer, in basis points
+        cap_x: u64,     // Lifetime cap on token X referral fees
+        cap_y: u64,     // Lifetime cap on token Y referral fees
+        bumps: SetPartnerBumps,
+    ) -> Result<()> {
+        require!(fee_share <= 10_000, AmmError::InvalidFee);
+
+        // Earnings carry over when an existing partner's terms are updated
+        self.partner.set_inner(Partner {
+            config: self.config.key(),
+            referrer,
+            fee_share,
+            cap_x,
+            cap_y,
+            earned_x: self.partner.earned_x,
+            earned_y: self.partner.earned_y,
+            bump: bumps.partner,
+        });
+
+        Ok(())
+    }
+}
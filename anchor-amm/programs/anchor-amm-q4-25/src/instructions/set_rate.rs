@@ -0,0 +1,34 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::AmmError,
+    state::{Config, Farm},
+};
+
+#[derive(Accounts)]
+pub struct SetRate<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+        constraint = config.authority.is_some() @ AmmError::NoAuthoritySet,
+        constraint = config.authority == Some(authority.key()) @ AmmError::InvalidAuthority,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        has_one = config,
+        seeds = [b"farm", config.key().as_ref(), farm.reward_mint.as_ref()],
+        bump = farm.bump,
+    )]
+    pub farm: Account<'info, Farm>,
+}
+
+impl<'info> SetRate<'info> {
+    pub fn set_rate(&mut self, reward_rate: u64) -> Result<()> {
+        // Accrue everything emitted at the old rate before switching
+        self.farm.update_rewards(Clock::get()?.unix_timestamp)?;
+        self.farm.reward_rate = reward_rate;
+        Ok(())
+    }
+}
@@ -0,0 +1,102 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_2022::Token2022,
+    token_interface::{transfer_checked, Mint, TokenAccount, TransferChecked},
+};
+
+use crate::{
+    errors::AmmError,
+    state::{Config, Farm, StakePosition},
+};
+
+#[derive(Accounts)]
+pub struct StakeLp<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        seeds = [b"lp", config.key().as_ref()],
+        bump = config.lp_bump,
+    )]
+    pub mint_lp: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        has_one = config,
+        seeds = [b"farm", config.key().as_ref(), farm.reward_mint.as_ref()],
+        bump = farm.bump,
+    )]
+    pub farm: Account<'info, Farm>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"position", farm.key().as_ref(), user.key().as_ref()],
+        bump,
+        space = StakePosition::DISCRIMINATOR.len() + StakePosition::INIT_SPACE,
+    )]
+    pub position: Account<'info, StakePosition>,
+    #[account(
+        mut,
+        associated_token::mint = mint_lp,
+        associated_token::authority = farm,
+        associated_token::token_program = lp_token_program,
+    )]
+    pub farm_lp: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_lp,
+        associated_token::authority = user,
+        associated_token::token_program = lp_token_program,
+    )]
+    pub user_lp: InterfaceAccount<'info, TokenAccount>,
+    pub lp_token_program: Program<'info, Token2022>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> StakeLp<'info> {
+    pub fn stake(&mut self, amount: u64, bumps: StakeLpBumps) -> Result<()> {
+        require!(amount > 0, AmmError::InvalidAmount);
+        require!(self.user_lp.amount >= amount, AmmError::InsufficientBalance);
+
+        // A freshly created position is all zeroes, so filling these in is a no-op afterwards
+        self.position.owner = self.user.key();
+        self.position.farm = self.farm.key();
+        self.position.bump = bumps.position;
+
+        self.farm.update_rewards(Clock::get()?.unix_timestamp)?;
+        self.position.settle(&self.farm)?;
+
+        self.deposit_lp_tokens(amount)?;
+
+        self.position.amount = self
+            .position
+            .amount
+            .checked_add(amount)
+            .ok_or(AmmError::Overflow)?;
+        self.farm.total_staked = self
+            .farm
+            .total_staked
+            .checked_add(amount)
+            .ok_or(AmmError::Overflow)?;
+        self.position.reward_debt = self.farm.reward_debt(self.position.amount)?;
+
+        Ok(())
+    }
+
+    pub fn deposit_lp_tokens(&self, amount: u64) -> Result<()> {
+        let cpi_accounts = TransferChecked {
+            from: self.user_lp.to_account_info(),
+            mint: self.mint_lp.to_account_info(),
+            to: self.farm_lp.to_account_info(),
+            authority: self.user.to_account_info(),
+        };
+
+        let ctx = CpiContext::new(self.lp_token_program.to_account_info(), cpi_accounts);
+        transfer_checked(ctx, amount, self.mint_lp.decimals)
+    }
+}
@@ -2,10 +2,15 @@ use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
     token::{transfer, Mint, Token, TokenAccount, Transfer},
+    token_interface,
 };
 use constant_product_curve::{ConstantProduct, LiquidityPair};
 
-use crate::{errors::AmmError, state::Config};
+use crate::{
+    errors::AmmError,
+    state::{Config, Partner},
+    wsol,
+};
 
 #[derive(Accounts)]
 pub struct Swap<'info> {
@@ -14,6 +19,7 @@ pub struct Swap<'info> {
     pub mint_x: Account<'info, Mint>,
     pub mint_y: Account<'info, Mint>,
     #[account(
+        mut,
         has_one = mint_x,
         has_one = mint_y,
         seeds = [b"config", config.seed.to_le_bytes().as_ref()],
@@ -25,7 +31,7 @@ pub struct Swap<'info> {
         seeds = [b"lp", config.key().as_ref()],
         bump = config.lp_bump,
     )]
-    pub mint_lp: Account<'info, Mint>,
+    pub mint_lp: InterfaceAccount<'info, token_interface::Mint>,
     #[account(
         mut,
         associated_token::mint = mint_x,
@@ -39,17 +45,29 @@ pub struct Swap<'info> {
     )]
     pub vault_y: Account<'info, TokenAccount>,
     #[account(
-        mut,
+        init_if_needed,
+        payer = user,
         associated_token::mint = mint_x,
         associated_token::authority = user,
     )]
     pub user_x: Account<'info, TokenAccount>,
     #[account(
-        mut,
+        init_if_needed,
+        payer = user,
         associated_token::mint = mint_y,
         associated_token::authority = user,
     )]
     pub user_y: Account<'info, TokenAccount>,
+    // Optional integrator that routed the trade, paid a slice of the input fee
+    #[account(
+        mut,
+        has_one = config,
+        seeds = [b"partner", config.key().as_ref(), partner.referrer.as_ref()],
+        bump = partner.bump,
+    )]
+    pub partner: Option<Account<'info, Partner>>,
+    #[account(mut)]
+    pub referrer_token: Option<Account<'info, TokenAccount>>,
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
     pub associated_token_program: Program<'info, AssociatedToken>,
@@ -57,13 +75,15 @@ pub struct Swap<'info> {
 
 impl<'info> Swap<'info> {
     pub fn swap(&mut self, is_x: bool, amount: u64, min: u64) -> Result<()> {
-        require!(self.config.locked == false, AmmError::PoolLocked);
+        require!(!self.config.locked, AmmError::PoolLocked);
         require!(amount > 0, AmmError::InvalidAmount);
+        let now = Clock::get()?.unix_timestamp;
+        let fee = self.config.swap_fee(now);
         let mut curve = ConstantProduct::init(
-            self.vault_x.amount,
-            self.vault_y.amount,
+            self.config.reserve_x,
+            self.config.reserve_y,
             self.mint_lp.supply,
-            self.config.fee,
+            fee,
             None,
         ).map_err(AmmError::from)?;
         let isx = match is_x {
@@ -73,11 +93,78 @@ impl<'info> Swap<'info> {
         let swaps = curve.swap(isx, amount, min).map_err(AmmError::from)?;
         require!(swaps.deposit != 0, AmmError::InvalidAmount);
 
-        self.deposit_tokens(is_x, swaps.deposit)?;
-        self.withdraw_tokens(!is_x, swaps.withdraw)?;
+        // The referral comes out of the fee, so the pool still receives at least amount - fee
+        let referral = self.referral_fee(is_x, swaps.deposit, fee)?;
+        let deposit = swaps.deposit - referral;
+        let withdraw = swaps.withdraw.min(self.config.max_swap_out(is_x, deposit)?);
+        require!(withdraw != 0, AmmError::InvalidAmount);
+        require!(withdraw >= min, AmmError::SlippageExceeded);
+
+        let temporary_x = wsol::is_temporary(&self.mint_x, &self.user_x);
+        let temporary_y = wsol::is_temporary(&self.mint_y, &self.user_y);
+        self.wrap_sol(is_x, swaps.deposit)?;
+        self.deposit_tokens(is_x, deposit)?;
+        if referral > 0 {
+            self.pay_referrer(is_x, referral)?;
+        }
+        self.withdraw_tokens(!is_x, withdraw)?;
+        wsol::unwrap_sol(&self.user_x, &self.user, &self.token_program, temporary_x)?;
+        wsol::unwrap_sol(&self.user_y, &self.user, &self.token_program, temporary_y)?;
+
+        let (x, y) = match is_x {
+            true => (
+                self.config.reserve_x.checked_add(deposit).ok_or(AmmError::Overflow)?,
+                self.config.reserve_y.checked_sub(withdraw).ok_or(AmmError::Underflow)?,
+            ),
+            false => (
+                self.config.reserve_x.checked_sub(withdraw).ok_or(AmmError::Underflow)?,
+                self.config.reserve_y.checked_add(deposit).ok_or(AmmError::Overflow)?,
+            ),
+        };
+        self.config.reserve_x = x;
+        self.config.reserve_y = y;
+        self.config.record_price(x, y, now);
         Ok(())
     }
 
+    // Referrer's slice of the input fee, limited by what is left of the partner's cap
+    pub fn referral_fee(&mut self, is_x: bool, amount: u64, fee: u16) -> Result<u64> {
+        let (partner, referrer_token) = match (&mut self.partner, &self.referrer_token) {
+            (Some(partner), Some(referrer_token)) => (partner, referrer_token),
+            (None, None) => return Ok(0),
+            _ => return err!(AmmError::InvalidReferrer),
+        };
+
+        let input_mint = match is_x {
+            true => self.mint_x.key(),
+            false => self.mint_y.key(),
+        };
+        require_keys_eq!(referrer_token.mint, input_mint, AmmError::InvalidToken);
+        require_keys_eq!(referrer_token.owner, partner.referrer, AmmError::InvalidReferrer);
+
+        Ok(partner.take_referral(is_x, amount, fee))
+    }
+
+    pub fn pay_referrer(&self, is_x: bool, amount: u64) -> Result<()> {
+        let referrer_token = self
+            .referrer_token
+            .as_ref()
+            .ok_or(AmmError::InvalidReferrer)?;
+        let from = match is_x {
+            true => &self.user_x,
+            false => &self.user_y,
+        };
+
+        let cpi_accounts = Transfer {
+            from: from.to_account_info(),
+            to: referrer_token.to_account_info(),
+            authority: self.user.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+        transfer(cpi_ctx, amount)
+    }
+
     pub fn deposit_tokens(&mut self, is_x: bool, amount: u64) -> Result<()> {
         let (from, to) = match is_x {
             true => (&self.user_x, &self.vault_x),
@@ -124,4 +211,19 @@ impl<'info> Swap<'info> {
         transfer(cpi_ctx, amount)?;
         Ok(())
     }
+
+    pub fn wrap_sol(&self, is_x: bool, amount: u64) -> Result<()> {
+        let (mint, user_ata) = match is_x {
+            true => (&self.mint_x, &self.user_x),
+            false => (&self.mint_y, &self.user_y),
+        };
+        wsol::wrap_sol(
+            mint,
+            user_ata,
+            &self.user,
+            &self.system_program,
+            &self.token_program,
+            amount,
+        )
+    }
 }
@@ -0,0 +1,54 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token::{Mint, Token, TokenAccount},
+    token_interface,
+};
+
+use crate::{errors::AmmError, state::Config};
+
+#[derive(Accounts)]
+pub struct SyncReserves<'info> {
+    pub mint_x: Account<'info, Mint>,
+    pub mint_y: Account<'info, Mint>,
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        seeds = [b"lp", config.key().as_ref()],
+        bump = config.lp_bump,
+    )]
+    pub mint_lp: InterfaceAccount<'info, token_interface::Mint>,
+    #[account(
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+    )]
+    pub vault_x: Account<'info, TokenAccount>,
+    #[account(
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+    )]
+    pub vault_y: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> SyncReserves<'info> {
+    pub fn sync(&mut self) -> Result<()> {
+        require!(!self.config.locked, AmmError::PoolLocked);
+        // Without LPs the first deposit sets the price, donations are left for skim
+        require!(self.mint_lp.supply > 0, AmmError::NoLiquidityInPool);
+
+        self.config.reserve_x = self.vault_x.amount;
+        self.config.reserve_y = self.vault_y.amount;
+        // Anyone can call sync, so a donation must not be able to push the dynamic fee up
+        self.config.reset_price(self.vault_x.amount, self.vault_y.amount);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_2022::Token2022,
+    token_interface::{transfer_checked, Mint, TokenAccount, TransferChecked},
+};
+
+use crate::{
+    errors::AmmError,
+    state::{Config, Farm, StakePosition},
+};
+
+#[derive(Accounts)]
+pub struct UnstakeLp<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        seeds = [b"lp", config.key().as_ref()],
+        bump = config.lp_bump,
+    )]
+    pub mint_lp: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        has_one = config,
+        seeds = [b"farm", config.key().as_ref(), farm.reward_mint.as_ref()],
+        bump = farm.bump,
+    )]
+    pub farm: Account<'info, Farm>,
+    #[account(
+        mut,
+        has_one = farm,
+        constraint = position.owner == user.key() @ AmmError::InvalidAuthority,
+        seeds = [b"position", farm.key().as_ref(), user.key().as_ref()],
+        bump = position.bump,
+    )]
+    pub position: Account<'info, StakePosition>,
+    #[account(
+        mut,
+        associated_token::mint = mint_lp,
+        associated_token::authority = farm,
+        associated_token::token_program = lp_token_program,
+    )]
+    pub farm_lp: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = mint_lp,
+        associated_token::authority = user,
+        associated_token::token_program = lp_token_program,
+    )]
+    pub user_lp: InterfaceAccount<'info, TokenAccount>,
+    pub lp_token_program: Program<'info, Token2022>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> UnstakeLp<'info> {
+    pub fn unstake(&mut self, amount: u64) -> Result<()> {
+        require!(amount > 0, AmmError::InvalidAmount);
+        require!(self.position.amount >= amount, AmmError::InsufficientBalance);
+
+        // Rewards earned so far stay claimable through harvest
+        self.farm.update_rewards(Clock::get()?.unix_timestamp)?;
+        self.position.settle(&self.farm)?;
+
+        self.withdraw_lp_tokens(amount)?;
+
+        self.position.amount -= amount;
+        self.farm.total_staked = self
+            .farm
+            .total_staked
+            .checked_sub(amount)
+            .ok_or(AmmError::Underflow)?;
+        self.position.reward_debt = self.farm.reward_debt(self.position.amount)?;
+
+        Ok(())
+    }
+
+    pub fn withdraw_lp_tokens(&self, amount: u64) -> Result<()> {
+        let cpi_accounts = TransferChecked {
+            from: self.farm_lp.to_account_info(),
+            mint: self.mint_lp.to_account_info(),
+            to: self.user_lp.to_account_info(),
+            authority: self.farm.to_account_info(),
+        };
+
+        let config_key = self.config.key();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"farm",
+            config_key.as_ref(),
+            self.farm.reward_mint.as_ref(),
+            &[self.farm.bump],
+        ]];
+
+        let ctx = CpiContext::new_with_signer(
+            self.lp_token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        transfer_checked(ctx, amount, self.mint_lp.decimals)
+    }
+}
@@ -1,11 +1,12 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{burn, transfer, Burn, Mint, Token, TokenAccount, Transfer},
+    token::{transfer, Mint, Token, TokenAccount, Transfer},
+    token_2022::Token2022,
+    token_interface::{self, burn, Burn},
 };
-use constant_product_curve::ConstantProduct;
 
-use crate::{errors::AmmError, state::Config};
+use crate::{errors::AmmError, state::Config, wsol};
 
 #[derive(Accounts)]
 pub struct Withdraw<'info> {
@@ -14,6 +15,7 @@ pub struct Withdraw<'info> {
     pub mint_x: Account<'info, Mint>,
     pub mint_y: Account<'info, Mint>,
     #[account(
+        mut,
         has_one = mint_x,
         has_one = mint_y,
         seeds = [b"config", config.seed.to_le_bytes().as_ref()],
@@ -25,7 +27,7 @@ pub struct Withdraw<'info> {
         seeds = [b"lp", config.key().as_ref()],
         bump = config.lp_bump,
     )]
-    pub mint_lp: Account<'info, Mint>,
+    pub mint_lp: InterfaceAccount<'info, token_interface::Mint>,
     #[account(
         mut,
         associated_token::mint = mint_x,
@@ -39,13 +41,15 @@ pub struct Withdraw<'info> {
     )]
     pub vault_y: Account<'info, TokenAccount>,
     #[account(
-        mut,
+        init_if_needed,
+        payer = user,
         associated_token::mint = mint_x,
         associated_token::authority = user,
     )]
     pub user_x: Account<'info, TokenAccount>,
     #[account(
-        mut,
+        init_if_needed,
+        payer = user,
         associated_token::mint = mint_y,
         associated_token::authority = user,
     )]
@@ -54,9 +58,11 @@ pub struct Withdraw<'info> {
         mut, 
         associated_token::mint = mint_lp,
         associated_token::authority = user,
+        associated_token::token_program = lp_token_program,
     )]
-    pub user_lp: Account<'info, TokenAccount>,
+    pub user_lp: InterfaceAccount<'info, token_interface::TokenAccount>,
     pub token_program: Program<'info, Token>,
+    pub lp_token_program: Program<'info, Token2022>,
     pub system_program: Program<'info, System>,
     pub associated_token_program: Program<'info, AssociatedToken>,
 }
@@ -68,32 +74,34 @@ impl<'info> Withdraw<'info> {
         min_x: u64,  // Minimum amount of token X that the user wants to receive
         min_y: u64,  // Minimum amount of token Y that the user wants to receive
     ) -> Result<()> {
-        require!(self.config.locked == false, AmmError::PoolLocked);
+        require!(!self.config.locked, AmmError::PoolLocked);
         require!(amount > 0, AmmError::InvalidAmount);
         require!(self.user_lp.amount >= amount, AmmError::InsufficientBalance);
 
-        let total_supply = self.mint_lp.supply;
         let (x, y) = match self.mint_lp.supply == 0 {
             true => (0u64, 0u64),
-            false => {
-                let amounts = ConstantProduct::xy_withdraw_amounts_from_l(
-                    self.vault_x.amount,
-                    self.vault_y.amount,
-                    total_supply,
-                    amount,
-                    6,
-                )
-                .map_err(AmmError::from)?;
-            (amounts.x, amounts.y)
-            }
+            false => self.config.withdraw_amounts(self.mint_lp.supply, amount)?,
         };
 
         require!(x >= min_x && y >= min_y, AmmError::SlippageExceeded);
 
+        let temporary_x = wsol::is_temporary(&self.mint_x, &self.user_x);
+        let temporary_y = wsol::is_temporary(&self.mint_y, &self.user_y);
         self.withdraw_tokens(true, x)?;
         self.withdraw_tokens(false, y)?;
         self.burn_lp_tokens(amount)?;
-        Ok(())
+        self.config.reserve_x = self
+            .config
+            .reserve_x
+            .checked_sub(x)
+            .ok_or(AmmError::Underflow)?;
+        self.config.reserve_y = self
+            .config
+            .reserve_y
+            .checked_sub(y)
+            .ok_or(AmmError::Underflow)?;
+        wsol::unwrap_sol(&self.user_x, &self.user, &self.token_program, temporary_x)?;
+        wsol::unwrap_sol(&self.user_y, &self.user, &self.token_program, temporary_y)
     }
 
     pub fn withdraw_tokens(&self, is_x: bool, amount: u64) -> Result<()> {
@@ -128,7 +136,7 @@ impl<'info> Withdraw<'info> {
     }
 
     pub fn burn_lp_tokens(&self, amount: u64) -> Result<()> {
-        let cpi_program = self.token_program.to_account_info();
+        let cpi_program = self.lp_token_program.to_account_info();
 
         let cpi_accounts = Burn {
             mint: self.mint_lp.to_account_info(),
@@ -1,10 +1,12 @@
 use anchor_lang::prelude::*;
 
-mod errors;
+pub mod errors;
 mod instructions;
-mod state;
+pub mod state;
+mod wsol;
 
 use instructions::*;
+use state::DynamicFee;
 declare_id!("9FVkz5L9LZix4zXYmqJLzKBwRpm9aJ539J26UsZSrLWN");
 
 #[program]
@@ -17,7 +19,8 @@ pub mod anchor_amm_q4_25 {
         fee: u16,
         authority: Option<Pubkey>,
     ) -> Result<()> {
-        ctx.accounts.init(seed, fee, authority, ctx.bumps)
+        ctx.accounts.init(seed, fee, authority, ctx.bumps)?;
+        ctx.accounts.create_lp_metadata()
     }
 
     pub fn deposit(ctx: Context<Deposit>, amount: u64, max_x: u64, max_y: u64) -> Result<()> {
@@ -31,4 +34,74 @@ pub mod anchor_amm_q4_25 {
     pub fn swap(ctx: Context<Swap>, is_x: bool, amount_in: u64, min_amount_out: u64) -> Result<()> {
         ctx.accounts.swap(is_x, amount_in, min_amount_out)
     }
+
+    pub fn sync(ctx: Context<SyncReserves>) -> Result<()> {
+        ctx.accounts.sync()
+    }
+
+    pub fn skim(ctx: Context<Skim>) -> Result<()> {
+        ctx.accounts.skim()
+    }
+
+    pub fn close_pool(ctx: Context<ClosePool>) -> Result<()> {
+        ctx.accounts.close_pool()
+    }
+
+    pub fn set_dynamic_fee(ctx: Context<SetDynamicFee>, dynamic_fee: Option<DynamicFee>) -> Result<()> {
+        ctx.accounts.set_dynamic_fee(dynamic_fee)
+    }
+
+    pub fn set_partner(
+        ctx: Context<SetPartner>,
+        referrer: Pubkey,
+        fee_share: u16,
+        cap_x: u64,
+        cap_y: u64,
+    ) -> Result<()> {
+        ctx.accounts.set_partner(referrer, fee_share, cap_x, cap_y, ctx.bumps)
+    }
+
+    pub fn initialize_farm(ctx: Context<InitializeFarm>, reward_rate: u64) -> Result<()> {
+        ctx.accounts.init_farm(reward_rate, ctx.bumps)
+    }
+
+    pub fn fund_farm(ctx: Context<FundFarm>, amount: u64) -> Result<()> {
+        ctx.accounts.fund(amount)
+    }
+
+    pub fn set_rate(ctx: Context<SetRate>, reward_rate: u64) -> Result<()> {
+        ctx.accounts.set_rate(reward_rate)
+    }
+
+    pub fn stake_lp(ctx: Context<StakeLp>, amount: u64) -> Result<()> {
+        ctx.accounts.stake(amount, ctx.bumps)
+    }
+
+    pub fn unstake_lp(ctx: Context<UnstakeLp>, amount: u64) -> Result<()> {
+        ctx.accounts.unstake(amount)
+    }
+
+    pub fn harvest(ctx: Context<Harvest>) -> Result<()> {
+        ctx.accounts.harvest()
+    }
+
+    pub fn place_order(
+        ctx: Context<PlaceOrder>,
+        seed: u64,
+        is_x: bool,
+        amount_in: u64,
+        min_amount_out: u64,
+        crank_fee: u64,
+    ) -> Result<()> {
+        ctx.accounts
+            .place_order(seed, is_x, amount_in, min_amount_out, crank_fee, ctx.bumps)
+    }
+
+    pub fn cancel_order(ctx: Context<CancelOrder>) -> Result<()> {
+        ctx.accounts.cancel_order()
+    }
+
+    pub fn fill_orders<'info>(ctx: Context<'_, '_, 'info, 'info, FillOrders<'info>>) -> Result<()> {
+        ctx.accounts.fill_orders(ctx.remaining_accounts)
+    }
 }
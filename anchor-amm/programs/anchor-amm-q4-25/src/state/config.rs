@@ -1,14 +1,134 @@
 use anchor_lang::prelude::*;
 
+use crate::errors::AmmError;
+
+// Scaling factor for the spot price tracked between swaps
+pub const PRICE_PRECISION: u128 = 1_000_000_000_000;
+
 #[account]
 #[derive(InitSpace)]
 pub struct Config {
-    pub seed: u64,                 // Seed to be able to create different pools / configs
-    pub authority: Option<Pubkey>, // If we want an authority to lock the config account
-    pub mint_x: Pubkey,            // Token X
-    pub mint_y: Pubkey,            // Token Y
-    pub fee: u16,                  // Swap fee in basis points
-    pub locked: bool,              // If the pool is locked
-    pub config_bump: u8,           // Bump seed for the config account
-    pub lp_bump: u8,               // Bump seed for the LP token
+    pub seed: u64,                       // Seed to be able to create different pools / configs
+    pub authority: Option<Pubkey>,       // If we want an authority to lock the config account
+    pub mint_x: Pubkey,                  // Token X
+    pub mint_y: Pubkey,                  // Token Y
+    pub fee: u16,                        // Swap fee in basis points, used when dynamic_fee is not set
+    pub reserve_x: u64,                  // Token X accounted to LPs, excludes direct donations to the vault
+    pub reserve_y: u64,                  // Token Y accounted to LPs, excludes direct donations to the vault
+    pub dynamic_fee: Option<DynamicFee>, // Volatility-based fee bounds, replaces `fee` when set
+    pub volatility: u64,                 // Recent price movement in basis points, decays over time
+    pub last_price: u128,                // Spot price (y per x, scaled by PRICE_PRECISION) after the last swap
+    pub last_swap: i64,                  // Timestamp of the last swap
+    pub locked: bool,                    // If the pool is locked
+    pub config_bump: u8,                 // Bump seed for the config account
+    pub lp_bump: u8,                     // Bump seed for the LP token
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct DynamicFee {
+    pub min_fee: u16,           // Fee charged in calm markets, in basis points
+    pub max_fee: u16,           // Upper bound for the fee, in basis points
+    pub volatility_factor: u16, // Fee basis points added per 10_000 bps of accumulated price movement
+    pub decay_period: u32,      // Seconds for accumulated volatility to decay back to zero
+}
+
+impl DynamicFee {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.min_fee <= self.max_fee && self.max_fee < 10_000,
+            AmmError::InvalidFee
+        );
+        require!(self.decay_period > 0, AmmError::InvalidAmount);
+        Ok(())
+    }
+}
+
+impl Config {
+    pub fn decayed_volatility(&self, now: i64) -> u64 {
+        let Some(dynamic_fee) = self.dynamic_fee else {
+            return self.volatility;
+        };
+        let elapsed = now.saturating_sub(self.last_swap).max(0) as u128;
+        let period = dynamic_fee.decay_period as u128;
+        if elapsed >= period {
+            return 0;
+        }
+        (self.volatility as u128 * (period - elapsed) / period) as u64
+    }
+
+    pub fn swap_fee(&self, now: i64) -> u16 {
+        let Some(dynamic_fee) = self.dynamic_fee else {
+            return self.fee;
+        };
+        let surcharge = self.decayed_volatility(now) as u128
+            * dynamic_fee.volatility_factor as u128
+            / 10_000;
+        (dynamic_fee.min_fee as u128 + surcharge).min(dynamic_fee.max_fee as u128) as u16
+    }
+
+    // Folds the price move caused by a swap into the volatility accumulator
+    pub fn record_price(&mut self, x: u64, y: u64, now: i64) {
+        if x == 0 {
+            return;
+        }
+        let price = y as u128 * PRICE_PRECISION / x as u128;
+        let mut volatility = self.decayed_volatility(now);
+        if self.last_price > 0 {
+            let movement = price.abs_diff(self.last_price) * 10_000 / self.last_price;
+            volatility = volatility.saturating_add(movement.min(u64::MAX as u128) as u64);
+        }
+        self.volatility = volatility;
+        self.last_price = price;
+        self.last_swap = now;
+    }
+
+    // Moves the reference price to the new reserves without counting it as volatility,
+    // for reserve changes that did not come from a swap
+    pub fn reset_price(&mut self, x: u64, y: u64) {
+        if x == 0 {
+            return;
+        }
+        self.last_price = y as u128 * PRICE_PRECISION / x as u128;
+    }
+
+    // Largest output for a swap adding `deposit` to the input reserve that keeps k from
+    // going down, so the curve's rounding can never leak value out of the pool
+    pub fn max_swap_out(&self, is_x: bool, deposit: u64) -> Result<u64> {
+        let (reserve_in, reserve_out) = match is_x {
+            true => (self.reserve_x, self.reserve_y),
+            false => (self.reserve_y, self.reserve_x),
+        };
+        let k = reserve_in as u128 * reserve_out as u128;
+        let reserve_in = (reserve_in as u128)
+            .checked_add(deposit as u128)
+            .ok_or(AmmError::Overflow)?;
+        require!(reserve_in > 0, AmmError::InvalidAmount);
+        Ok(reserve_out - k.div_ceil(reserve_in) as u64)
+    }
+
+    // Tokens owed for minting `amount` LP, rounded up so new LPs never dilute existing ones
+    pub fn deposit_amounts(&self, supply: u64, amount: u64) -> Result<(u64, u64)> {
+        Ok((
+            pro_rata(self.reserve_x, amount, supply, true)?,
+            pro_rata(self.reserve_y, amount, supply, true)?,
+        ))
+    }
+
+    // Tokens paid out for burning `amount` LP, rounded down so the pool keeps the dust
+    pub fn withdraw_amounts(&self, supply: u64, amount: u64) -> Result<(u64, u64)> {
+        Ok((
+            pro_rata(self.reserve_x, amount, supply, false)?,
+            pro_rata(self.reserve_y, amount, supply, false)?,
+        ))
+    }
+}
+
+fn pro_rata(reserve: u64, amount: u64, supply: u64, round_up: bool) -> Result<u64> {
+    require!(supply > 0, AmmError::InvalidAmount);
+    let numerator = reserve as u128 * amount as u128;
+    let share = match round_up {
+        true => numerator.div_ceil(supply as u128),
+        false => numerator / supply as u128,
+    };
+    u64::try_from(share).map_err(|_| AmmError::Overflow.into())
 }
@@ -0,0 +1,79 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::AmmError;
+
+// Scaling factor for the reward-per-share accumulator
+pub const ACC_PRECISION: u128 = 1_000_000_000_000;
+
+#[account]
+#[derive(InitSpace)]
+pub struct Farm {
+    pub config: Pubkey,               // Pool whose LP tokens are staked in this farm
+    pub reward_mint: Pubkey,          // Token paid out to stakers
+    pub reward_rate: u64,             // Reward tokens emitted per second across all stakers
+    pub acc_reward_per_share: u128,   // Rewards accrued per staked LP token, scaled by ACC_PRECISION
+    pub last_update: i64,             // Last time the accumulator was brought up to date
+    pub total_staked: u64,            // LP tokens currently staked
+    pub bump: u8,                     // Bump seed for the farm account
+}
+
+impl Farm {
+    pub fn update_rewards(&mut self, now: i64) -> Result<()> {
+        if now <= self.last_update {
+            return Ok(());
+        }
+
+        if self.total_staked > 0 {
+            let elapsed = (now - self.last_update) as u128;
+            let rewards = elapsed
+                .checked_mul(self.reward_rate as u128)
+                .ok_or(AmmError::Overflow)?;
+            let per_share = rewards
+                .checked_mul(ACC_PRECISION)
+                .ok_or(AmmError::Overflow)?
+                / self.total_staked as u128;
+            self.acc_reward_per_share = self
+                .acc_reward_per_share
+                .checked_add(per_share)
+                .ok_or(AmmError::Overflow)?;
+        }
+
+        self.last_update = now;
+        Ok(())
+    }
+
+    pub fn reward_debt(&self, amount: u64) -> Result<u128> {
+        (amount as u128)
+            .checked_mul(self.acc_reward_per_share)
+            .map(|debt| debt / ACC_PRECISION)
+            .ok_or(AmmError::Overflow.into())
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct StakePosition {
+    pub owner: Pubkey,        // LP who owns the position
+    pub farm: Pubkey,         // Farm the position belongs to
+    pub amount: u64,          // LP tokens staked
+    pub reward_debt: u128,    // Rewards already accounted for at the current stake amount
+    pub pending_rewards: u64, // Settled rewards not yet harvested
+    pub bump: u8,             // Bump seed for the position account
+}
+
+impl StakePosition {
+    // Moves everything accrued since the last settlement into pending_rewards.
+    // Must be called after Farm::update_rewards and before changing amount.
+    pub fn settle(&mut self, farm: &Farm) -> Result<()> {
+        let accrued = farm
+            .reward_debt(self.amount)?
+            .checked_sub(self.reward_debt)
+            .ok_or(AmmError::Underflow)?;
+        let accrued = u64::try_from(accrued).map_err(|_| AmmError::Overflow)?;
+        self.pending_rewards = self
+            .pending_rewards
+            .checked_add(accrued)
+            .ok_or(AmmError::Overflow)?;
+        Ok(())
+    }
+}
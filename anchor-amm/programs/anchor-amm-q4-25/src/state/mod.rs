@@ -1,3 +1,9 @@
 pub mod config;
+pub mod farm;
+pub mod order;
+pub mod partner;
 
 pub use config::*;
+pub use farm::*;
+pub use order::*;
+pub use partner::*;
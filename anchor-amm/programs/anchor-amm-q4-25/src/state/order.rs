@@ -0,0 +1,32 @@
+use anchor_lang::prelude::*;
+
+use crate::state::Config;
+
+#[account]
+#[derive(InitSpace)]
+pub struct Order {
+    pub config: Pubkey,      // Pool the order rests on
+    pub owner: Pubkey,       // Wallet that placed the order and receives the output
+    pub seed: u64,           // Seed to let one owner keep several orders on the same pool
+    pub is_x: bool,          // True when selling token X for token Y
+    pub amount_in: u64,      // Tokens escrowed in the order vault
+    pub min_amount_out: u64, // Output the pool must pay for the order to fill, i.e. the limit price
+    pub crank_fee: u64,      // Lamports paid to whoever fills the order
+    pub bump: u8,            // Bump seed for the order account
+}
+
+impl Order {
+    pub fn mint_in(&self, config: &Config) -> Pubkey {
+        match self.is_x {
+            true => config.mint_x,
+            false => config.mint_y,
+        }
+    }
+
+    pub fn mint_out(&self, config: &Config) -> Pubkey {
+        match self.is_x {
+            true => config.mint_y,
+            false => config.mint_x,
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use anchor_lang::prelude::*;
+
+#[account]
+#[derive(InitSpace)]
+pub struct Partner {
+    pub config: Pubkey,   // Pool the partner routes flow to
+    pub referrer: Pubkey, // Wallet that owns the referral token accounts
+    pub fee_share: u16,   // Share of the swap fee paid to the referrer, in basis points
+    pub cap_x: u64,       // Max token X the referrer can ever earn from this pool
+    pub cap_y: u64,       // Max token Y the referrer can ever earn from this pool
+    pub earned_x: u64,    // Token X paid out to the referrer so far
+    pub earned_y: u64,    // Token Y paid out to the referrer so far
+    pub bump: u8,         // Bump seed for the partner account
+}
+
+impl Partner {
+    // Referrer's slice of the input fee, limited by what is left of the cap, recorded as earned
+    pub fn take_referral(&mut self, is_x: bool, amount: u64, fee: u16) -> u64 {
+        let fee_amount = amount as u128 * fee as u128 / 10_000;
+        let share = (fee_amount * self.fee_share as u128 / 10_000) as u64;
+
+        let (cap, earned) = match is_x {
+            true => (self.cap_x, &mut self.earned_x),
+            false => (self.cap_y, &mut self.earned_y),
+        };
+        let referral = share.min(cap.saturating_sub(*earned));
+        *earned += referral;
+
+        referral
+    }
+}
@@ -0,0 +1,59 @@
+use anchor_lang::{prelude::*, system_program};
+use anchor_spl::token::{
+    close_account, spl_token::native_mint, sync_native, CloseAccount, Mint, SyncNative, Token,
+    TokenAccount,
+};
+
+// A user's WSOL account is only unwrapped when it held nothing going into the instruction,
+// i.e. it was created (or is an empty leftover) just to carry this instruction's native SOL.
+// Accounts that already held WSOL are the user's own and are left open with their balance
+pub fn is_temporary(mint: &Account<Mint>, user_ata: &Account<TokenAccount>) -> bool {
+    mint.key() == native_mint::ID && user_ata.amount == 0
+}
+
+// Tops up the user's WSOL account with lamports when the side is native SOL
+pub fn wrap_sol<'info>(
+    mint: &Account<'info, Mint>,
+    user_ata: &Account<'info, TokenAccount>,
+    user: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    token_program: &Program<'info, Token>,
+    amount: u64,
+) -> Result<()> {
+    if mint.key() != native_mint::ID || user_ata.amount >= amount {
+        return Ok(());
+    }
+
+    let cpi_accounts = system_program::Transfer {
+        from: user.to_account_info(),
+        to: user_ata.to_account_info(),
+    };
+    let ctx = CpiContext::new(system_program.to_account_info(), cpi_accounts);
+    system_program::transfer(ctx, amount - user_ata.amount)?;
+
+    let cpi_accounts = SyncNative {
+        account: user_ata.to_account_info(),
+    };
+    let ctx = CpiContext::new(token_program.to_account_info(), cpi_accounts);
+    sync_native(ctx)
+}
+
+// Closes a temporary WSOL account so its balance ends up back in the user's wallet as lamports
+pub fn unwrap_sol<'info>(
+    user_ata: &Account<'info, TokenAccount>,
+    user: &Signer<'info>,
+    token_program: &Program<'info, Token>,
+    temporary: bool,
+) -> Result<()> {
+    if !temporary {
+        return Ok(());
+    }
+
+    let cpi_accounts = CloseAccount {
+        account: user_ata.to_account_info(),
+        destination: user.to_account_info(),
+        authority: user.to_account_info(),
+    };
+    let ctx = CpiContext::new(token_program.to_account_info(), cpi_accounts);
+    close_account(ctx)
+}
@@ -5,13 +5,18 @@ import { Keypair, PublicKey } from "@solana/web3.js";
 import {
   ASSOCIATED_TOKEN_PROGRAM_ID,
   TOKEN_PROGRAM_ID,
+  TOKEN_2022_PROGRAM_ID,
   createMint,
   getAssociatedTokenAddressSync,
   createAccount,
   mintTo,
   getAccount,
   getMint,
+  NATIVE_MINT,
+  createTransferInstruction,
+  createWrappedNativeAccount,
 } from "@solana/spl-token";
+import { expect } from "chai";
 
 describe("anchor-amm-q4-25", () => {
   // Configure the client to use the local cluster.
@@ -36,6 +41,31 @@ describe("anchor-amm-q4-25", () => {
     program.programId
   );
 
+  const TOKEN_METADATA_PROGRAM_ID = new PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+  const metadataAddress = (mint: PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("metadata"), TOKEN_METADATA_PROGRAM_ID.toBuffer(), mint.toBuffer()],
+      TOKEN_METADATA_PROGRAM_ID
+    )[0];
+
+  // Leading fields of a token metadata account: key, update authority, mint, then the
+  // borsh name and symbol strings, which the program pads with NULs to their max length
+  const decodeMetadata = (data: Buffer) => {
+    const readString = (offset: number) => {
+      const length = data.readUInt32LE(offset);
+      const value = data.subarray(offset + 4, offset + 4 + length).toString("utf8");
+      return { value: value.replace(/\0+$/, ""), end: offset + 4 + length };
+    };
+    const name = readString(65);
+    const symbol = readString(name.end);
+    return {
+      updateAuthority: new PublicKey(data.subarray(1, 33)),
+      mint: new PublicKey(data.subarray(33, 65)),
+      name: name.value,
+      symbol: symbol.value,
+    };
+  };
+
   let mintX: PublicKey;
   let mintY: PublicKey;
   let vaultX: PublicKey;
@@ -70,7 +100,7 @@ describe("anchor-amm-q4-25", () => {
     // Calculate user token accounts
     userX = getAssociatedTokenAddressSync(mintX, wallet.publicKey);
     userY = getAssociatedTokenAddressSync(mintY, wallet.publicKey);
-    userLp = getAssociatedTokenAddressSync(mintLp, wallet.publicKey);
+    userLp = getAssociatedTokenAddressSync(mintLp, wallet.publicKey, false, TOKEN_2022_PROGRAM_ID);
 
     // Create user token accounts and mint some tokens
     await createAccount(connection, wallet.payer, mintX, wallet.publicKey);
@@ -89,7 +119,7 @@ describe("anchor-amm-q4-25", () => {
 
   it("Initialize AMM", async () => {
     const tx = await program.methods
-      .initialize(seed, fee, null)
+      .initialize(seed, fee, wallet.publicKey)
       .accountsStrict({
         initializer: wallet.publicKey,
         mintX: mintX,
@@ -98,9 +128,15 @@ describe("anchor-amm-q4-25", () => {
         vaultX: vaultX,
         vaultY: vaultY,
         config: config,
+        lpMetadata: metadataAddress(mintLp),
+        metadataX: null,
+        metadataY: null,
         tokenProgram: TOKEN_PROGRAM_ID,
+        lpTokenProgram: TOKEN_2022_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        metadataProgram: TOKEN_METADATA_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
       })
       .rpc();
 
@@ -115,6 +151,17 @@ describe("anchor-amm-q4-25", () => {
     console.log("  Locked:", configAccount.locked);
     console.log("  Mint X:", configAccount.mintX.toBase58());
     console.log("  Mint Y:", configAccount.mintY.toBase58());
+
+    // LP mint gets Metaplex metadata named after the pair. Neither test mint has metadata
+    // of its own, so each side falls back to the start of its address
+    const lpMetadata = await connection.getAccountInfo(metadataAddress(mintLp));
+    const metadata = decodeMetadata(lpMetadata.data);
+    const [symbolX, symbolY] = [mintX.toBase58().slice(0, 4), mintY.toBase58().slice(0, 4)];
+    expect(lpMetadata.owner.equals(TOKEN_METADATA_PROGRAM_ID)).to.be.true;
+    expect(metadata.updateAuthority.equals(config)).to.be.true;
+    expect(metadata.mint.equals(mintLp)).to.be.true;
+    expect(metadata.name).to.equal(`${symbolX}-${symbolY} LP`);
+    expect(metadata.symbol).to.equal(`${symbolX}${symbolY}LP`);
   });
 
   it("Deposit liquidity", async () => {
@@ -144,6 +191,7 @@ describe("anchor-amm-q4-25", () => {
         userY: userY,
         userLp: userLp,
         tokenProgram: TOKEN_PROGRAM_ID,
+        lpTokenProgram: TOKEN_2022_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
       })
@@ -152,10 +200,10 @@ describe("anchor-amm-q4-25", () => {
     // Get balances after
     const userXAfter = await getAccount(connection, userX);
     const userYAfter = await getAccount(connection, userY);
-    const userLpAfter = await getAccount(connection, userLp);
+    const userLpAfter = await getAccount(connection, userLp, undefined, TOKEN_2022_PROGRAM_ID);
     const vaultXAfter = await getAccount(connection, vaultX);
     const vaultYAfter = await getAccount(connection, vaultY);
-    const lpMintAfter = await getMint(connection, mintLp);
+    const lpMintAfter = await getMint(connection, mintLp, undefined, TOKEN_2022_PROGRAM_ID);
 
     console.log("\n✅ Deposit Liquidity");
     console.log("Transaction signature:", tx);
@@ -196,6 +244,8 @@ describe("anchor-amm-q4-25", () => {
         vaultY: vaultY,
         userX: userX,
         userY: userY,
+        partner: null,
+        referrerToken: null,
         tokenProgram: TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
@@ -219,6 +269,118 @@ describe("anchor-amm-q4-25", () => {
     console.log("  Vault Y:", Number(vaultYAfter.amount) / 1e6, "tokens");
   });
 
+  it("Swaps with a volatility-aware dynamic fee", async () => {
+    const before = await program.account.config.fetch(config);
+    expect(before.lastPrice.toString()).to.not.equal("0");
+
+    await program.methods
+      .setDynamicFee({ minFee: 30, maxFee: 500, volatilityFactor: 10_000, decayPeriod: 600 })
+      .accountsStrict({
+        authority: wallet.publicKey,
+        config: config,
+      })
+      .rpc();
+
+    await program.methods
+      .swap(false, new anchor.BN(10_000_000), new anchor.BN(1))
+      .accountsStrict({
+        user: wallet.publicKey,
+        mintX: mintX,
+        mintY: mintY,
+        config: config,
+        mintLp: mintLp,
+        vaultX: vaultX,
+        vaultY: vaultY,
+        userX: userX,
+        userY: userY,
+        partner: null,
+        referrerToken: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+
+    // The earlier swap set the reference price, so this one's move registers as volatility
+    // and lifts the fee off its floor. Its decay over time is covered by the LiteSVM tests
+    const configAccount = await program.account.config.fetch(config);
+    const { minFee, maxFee, volatilityFactor } = configAccount.dynamicFee;
+    const surcharge = configAccount.volatility.toNumber() * volatilityFactor / 10_000;
+    const fee = Math.min(maxFee, minFee + surcharge);
+    expect(configAccount.volatility.toNumber()).to.be.greaterThan(0);
+    expect(fee).to.be.greaterThan(minFee);
+    expect(configAccount.lastSwap.toNumber()).to.be.greaterThan(0);
+    expect(configAccount.lastPrice.toString()).to.equal(
+      configAccount.reserveY.mul(new anchor.BN(10).pow(new anchor.BN(12))).div(configAccount.reserveX).toString()
+    );
+  });
+
+  it("Pays a referral fee to a partner", async () => {
+    const referrer = Keypair.generate();
+    const [partner] = PublicKey.findProgramAddressSync(
+      [Buffer.from("partner"), config.toBuffer(), referrer.publicKey.toBuffer()],
+      program.programId
+    );
+    const referrerToken = await createAccount(connection, wallet.payer, mintX, referrer.publicKey);
+
+    // Back to the flat 2% fee so the referral slice is predictable
+    await program.methods
+      .setDynamicFee(null)
+      .accountsStrict({
+        authority: wallet.publicKey,
+        config: config,
+      })
+      .rpc();
+
+    // 50% of the swap fee, capped at 0.075 token X over the partner's lifetime
+    await program.methods
+      .setPartner(referrer.publicKey, 5_000, new anchor.BN(75_000), new anchor.BN(0))
+      .accountsStrict({
+        authority: wallet.publicKey,
+        config: config,
+        partner: partner,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    // Swaps 5 X, whose 2% fee is 0.1 X, and returns what the referrer and partner got from it.
+    // `min` only keeps the otherwise identical transactions apart
+    const swapWithReferral = async (min: number) => {
+      const referrerBefore = await getAccount(connection, referrerToken);
+      await program.methods
+        .swap(true, new anchor.BN(5_000_000), new anchor.BN(min))
+        .accountsStrict({
+          user: wallet.publicKey,
+          mintX: mintX,
+          mintY: mintY,
+          config: config,
+          mintLp: mintLp,
+          vaultX: vaultX,
+          vaultY: vaultY,
+          userX: userX,
+          userY: userY,
+          partner: partner,
+          referrerToken: referrerToken,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+      const referrerAfter = await getAccount(connection, referrerToken);
+      const partnerAccount = await program.account.partner.fetch(partner);
+      return {
+        paid: Number(referrerAfter.amount - referrerBefore.amount),
+        earnedX: partnerAccount.earnedX.toNumber(),
+        earnedY: partnerAccount.earnedY.toNumber(),
+      };
+    };
+
+    // Half the fee, then only what is left under the cap, then nothing
+    expect(await swapWithReferral(1)).to.deep.equal({ paid: 50_000, earnedX: 50_000, earnedY: 0 });
+    expect(await swapWithReferral(2)).to.deep.equal({ paid: 25_000, earnedX: 75_000, earnedY: 0 });
+    expect(await swapWithReferral(3)).to.deep.equal({ paid: 0, earnedX: 75_000, earnedY: 0 });
+  });
+
   it("Withdraw liquidity", async () => {
     const lpToBurn = new anchor.BN(50_000_000); // Burn 50 LP tokens
     const minX = new anchor.BN(40_000_000); // Minimum 40 tokens X out
@@ -227,7 +389,7 @@ describe("anchor-amm-q4-25", () => {
     // Get balances before
     const userXBefore = await getAccount(connection, userX);
     const userYBefore = await getAccount(connection, userY);
-    const userLpBefore = await getAccount(connection, userLp);
+    const userLpBefore = await getAccount(connection, userLp, undefined, TOKEN_2022_PROGRAM_ID);
     
     console.log("\n🏧 Before Withdraw:");
     console.log("  User X balance:", Number(userXBefore.amount) / 1e6, "tokens");
@@ -248,6 +410,7 @@ describe("anchor-amm-q4-25", () => {
         userY: userY,
         userLp: userLp,
         tokenProgram: TOKEN_PROGRAM_ID,
+        lpTokenProgram: TOKEN_2022_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
       })
@@ -256,10 +419,10 @@ describe("anchor-amm-q4-25", () => {
     // Get balances after
     const userXAfter = await getAccount(connection, userX);
     const userYAfter = await getAccount(connection, userY);
-    const userLpAfter = await getAccount(connection, userLp);
+    const userLpAfter = await getAccount(connection, userLp, undefined, TOKEN_2022_PROGRAM_ID);
     const vaultXAfter = await getAccount(connection, vaultX);
     const vaultYAfter = await getAccount(connection, vaultY);
-    const lpMintAfter = await getMint(connection, mintLp);
+    const lpMintAfter = await getMint(connection, mintLp, undefined, TOKEN_2022_PROGRAM_ID);
 
     console.log("\n✅ Withdraw Liquidity");
     console.log("Transaction signature:", tx);
@@ -272,4 +435,513 @@ describe("anchor-amm-q4-25", () => {
     console.log("  Vault Y:", Number(vaultYAfter.amount) / 1e6, "tokens");
     console.log("  Total LP Supply:", Number(lpMintAfter.supply) / 1e6, "tokens");
   });
-});
\ No newline at end of file
+
+  it("Syncs reserves after a direct donation", async () => {
+    // Donate 1 token of X straight into the vault
+    await anchor.web3.sendAndConfirmTransaction(
+      connection,
+      new anchor.web3.Transaction().add(
+        createTransferInstruction(userX, vaultX, wallet.publicKey, 1_000_000)
+      ),
+      [wallet.payer]
+    );
+
+    const before = await program.account.config.fetch(config);
+    await program.methods
+      .sync()
+      .accountsStrict({
+        mintX: mintX,
+        mintY: mintY,
+        config: config,
+        mintLp: mintLp,
+        vaultX: vaultX,
+        vaultY: vaultY,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+    const after = await program.account.config.fetch(config);
+
+    // The donation is now accounted to LPs, so the reserves match the vaults again
+    expect(after.reserveX.sub(before.reserveX).toNumber()).to.equal(1_000_000);
+    expect(after.reserveX.toString()).to.equal((await getAccount(connection, vaultX)).amount.toString());
+    expect(after.reserveY.toString()).to.equal((await getAccount(connection, vaultY)).amount.toString());
+  });
+
+  it("Skims a direct donation to the authority", async () => {
+    await anchor.web3.sendAndConfirmTransaction(
+      connection,
+      new anchor.web3.Transaction().add(
+        createTransferInstruction(userY, vaultY, wallet.publicKey, 2_000_000)
+      ),
+      [wallet.payer]
+    );
+
+    const before = await program.account.config.fetch(config);
+    const userYBefore = await getAccount(connection, userY);
+    await program.methods
+      .skim()
+      .accountsStrict({
+        authority: wallet.publicKey,
+        mintX: mintX,
+        mintY: mintY,
+        config: config,
+        vaultX: vaultX,
+        vaultY: vaultY,
+        authorityX: userX,
+        authorityY: userY,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    const after = await program.account.config.fetch(config);
+
+    // Only the excess over the reserves goes out, the LPs' share stays put
+    const userYAfter = await getAccount(connection, userY);
+    expect(Number(userYAfter.amount - userYBefore.amount)).to.equal(2_000_000);
+    expect(after.reserveX.toString()).to.equal(before.reserveX.toString());
+    expect(after.reserveY.toString()).to.equal(before.reserveY.toString());
+    expect((await getAccount(connection, vaultY)).amount.toString()).to.equal(after.reserveY.toString());
+  });
+
+  describe("Native SOL pool", () => {
+    const solSeed = new anchor.BN(12);
+    let solConfig: PublicKey;
+    let solMintLp: PublicKey;
+
+    it("Deposits and swaps native SOL without a WSOL account", async () => {
+      [solConfig] = PublicKey.findProgramAddressSync(
+        [Buffer.from("config"), solSeed.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+      [solMintLp] = PublicKey.findProgramAddressSync(
+        [Buffer.from("lp"), solConfig.toBuffer()],
+        program.programId
+      );
+      const accounts = {
+        user: wallet.publicKey,
+        mintX: NATIVE_MINT,
+        mintY: mintY,
+        config: solConfig,
+        mintLp: solMintLp,
+        vaultX: getAssociatedTokenAddressSync(NATIVE_MINT, solConfig, true),
+        vaultY: getAssociatedTokenAddressSync(mintY, solConfig, true),
+        userX: getAssociatedTokenAddressSync(NATIVE_MINT, wallet.publicKey),
+        userY: userY,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      };
+
+      await program.methods
+        .initialize(solSeed, fee, wallet.publicKey)
+        .accountsStrict({
+          initializer: wallet.publicKey,
+          mintX: NATIVE_MINT,
+          mintY: mintY,
+          mintLp: solMintLp,
+          vaultX: accounts.vaultX,
+          vaultY: accounts.vaultY,
+          config: solConfig,
+          lpMetadata: metadataAddress(solMintLp),
+          metadataX: null,
+          metadataY: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          lpTokenProgram: TOKEN_2022_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          metadataProgram: TOKEN_METADATA_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        })
+        .rpc();
+
+      await program.methods
+        .deposit(new anchor.BN(10_000_000), new anchor.BN(10_000_000), new anchor.BN(10_000_000))
+        .accountsStrict({
+          ...accounts,
+          userLp: getAssociatedTokenAddressSync(solMintLp, wallet.publicKey, false, TOKEN_2022_PROGRAM_ID),
+          lpTokenProgram: TOKEN_2022_PROGRAM_ID,
+        })
+        .rpc();
+
+      await program.methods
+        .swap(true, new anchor.BN(1_000_000), new anchor.BN(1))
+        .accountsStrict({ ...accounts, partner: null, referrerToken: null })
+        .rpc();
+
+      // The temporary WSOL account is closed once the lamports are in the vault
+      const wsolAccount = await connection.getAccountInfo(accounts.userX);
+      console.log("\n◎ Native SOL pool");
+      console.log("  Vault X (SOL):", Number((await getAccount(connection, accounts.vaultX)).amount) / 1e9);
+      console.log("  User WSOL account closed:", wsolAccount === null);
+      expect(wsolAccount).to.be.null;
+
+      // A WSOL account the user already holds a balance in is spent from and left open
+      await createWrappedNativeAccount(connection, wallet.payer, wallet.publicKey, 2_000_000);
+      await program.methods
+        .swap(true, new anchor.BN(1_000_000), new anchor.BN(1))
+        .accountsStrict({ ...accounts, partner: null, referrerToken: null })
+        .rpc();
+      expect(Number((await getAccount(connection, accounts.userX)).amount)).to.equal(1_000_000);
+    });
+  });
+
+  describe("LP farm", () => {
+    const rewardRate = new anchor.BN(1_000); // reward tokens per second
+    let rewardMint: PublicKey;
+    let farm: PublicKey;
+    let farmLp: PublicKey;
+    let rewardVault: PublicKey;
+    let authorityReward: PublicKey;
+    let position: PublicKey;
+
+    before(async () => {
+      rewardMint = await createMint(connection, wallet.payer, wallet.publicKey, null, 6);
+      [farm] = PublicKey.findProgramAddressSync(
+        [Buffer.from("farm"), config.toBuffer(), rewardMint.toBuffer()],
+        program.programId
+      );
+      [position] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), farm.toBuffer(), wallet.publicKey.toBuffer()],
+        program.programId
+      );
+      farmLp = getAssociatedTokenAddressSync(mintLp, farm, true, TOKEN_2022_PROGRAM_ID);
+      rewardVault = getAssociatedTokenAddressSync(rewardMint, farm, true);
+      authorityReward = getAssociatedTokenAddressSync(rewardMint, wallet.publicKey);
+
+      await createAccount(connection, wallet.payer, rewardMint, wallet.publicKey);
+      await mintTo(connection, wallet.payer, rewardMint, authorityReward, wallet.publicKey, 1_000_000_000);
+    });
+
+    it("Initializes and funds a farm", async () => {
+      await program.methods
+        .initializeFarm(rewardRate)
+        .accountsStrict({
+          authority: wallet.publicKey,
+          config,
+          mintLp,
+          rewardMint,
+          farm,
+          farmLp,
+          rewardVault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          lpTokenProgram: TOKEN_2022_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+      await program.methods
+        .fundFarm(new anchor.BN(500_000_000))
+        .accountsStrict({
+          authority: wallet.publicKey,
+          config,
+          rewardMint,
+          farm,
+          rewardVault,
+          authorityReward,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+      const farmAccount = await program.account.farm.fetch(farm);
+      expect(farmAccount.rewardRate.toString()).to.equal(rewardRate.toString());
+      expect(farmAccount.totalStaked.toNumber()).to.equal(0);
+      expect(Number((await getAccount(connection, rewardVault)).amount)).to.equal(500_000_000);
+    });
+
+    // Reward accrual needs the clock to move, so harvesting is covered by the LiteSVM
+    // farm test in crates/amm-math, which warps it
+    it("Stakes and unstakes LP", async () => {
+      const stakeAmount = new anchor.BN(10_000_000);
+      const lpBefore = (await getAccount(connection, userLp, undefined, TOKEN_2022_PROGRAM_ID)).amount;
+
+      await program.methods
+        .stakeLp(stakeAmount)
+        .accountsStrict({
+          user: wallet.publicKey,
+          config,
+          mintLp,
+          farm,
+          position,
+          farmLp,
+          userLp,
+          lpTokenProgram: TOKEN_2022_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+      let positionAccount = await program.account.stakePosition.fetch(position);
+      let farmAccount = await program.account.farm.fetch(farm);
+      expect(positionAccount.owner.toBase58()).to.equal(wallet.publicKey.toBase58());
+      expect(positionAccount.amount.toString()).to.equal(stakeAmount.toString());
+      // First staker, so nothing had accrued per share yet
+      expect(positionAccount.rewardDebt.toNumber()).to.equal(0);
+      expect(farmAccount.totalStaked.toString()).to.equal(stakeAmount.toString());
+      expect(Number((await getAccount(connection, farmLp, undefined, TOKEN_2022_PROGRAM_ID)).amount)).to.equal(
+        stakeAmount.toNumber()
+      );
+
+      await program.methods
+        .unstakeLp(stakeAmount)
+        .accountsStrict({
+          user: wallet.publicKey,
+          config,
+          mintLp,
+          farm,
+          position,
+          farmLp,
+          userLp,
+          lpTokenProgram: TOKEN_2022_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+      positionAccount = await program.account.stakePosition.fetch(position);
+      farmAccount = await program.account.farm.fetch(farm);
+      expect(positionAccount.amount.toNumber()).to.equal(0);
+      expect(positionAccount.rewardDebt.toNumber()).to.equal(0);
+      expect(farmAccount.totalStaked.toNumber()).to.equal(0);
+      expect((await getAccount(connection, userLp, undefined, TOKEN_2022_PROGRAM_ID)).amount).to.equal(lpBefore);
+    });
+  });
+
+  describe("Limit orders", () => {
+    const crankFee = new anchor.BN(1_000_000); // lamports
+    const cranker = Keypair.generate();
+
+    const orderAddress = (orderSeed: anchor.BN) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("order"), config.toBuffer(), wallet.publicKey.toBuffer(), orderSeed.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      )[0];
+
+    const placeOrder = async (orderSeed: anchor.BN, amountIn: anchor.BN, minAmountOut: anchor.BN) => {
+      const order = orderAddress(orderSeed);
+      const orderVault = getAssociatedTokenAddressSync(mintX, order, true);
+      await program.methods
+        .placeOrder(orderSeed, true, amountIn, minAmountOut, crankFee)
+        .accountsStrict({
+          owner: wallet.publicKey,
+          config,
+          mintIn: mintX,
+          ownerIn: userX,
+          order,
+          orderVault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+      return { order, orderVault };
+    };
+
+    const fillOrders = async (orders: { order: PublicKey; orderVault: PublicKey }[]) =>
+      program.methods
+        .fillOrders()
+        .accountsStrict({
+          cranker: cranker.publicKey,
+          config,
+          mintLp,
+          vaultX,
+          vaultY,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(
+          orders.flatMap(({ order, orderVault }) => [
+            { pubkey: order, isSigner: false, isWritable: true },
+            { pubkey: orderVault, isSigner: false, isWritable: true },
+            { pubkey: wallet.publicKey, isSigner: false, isWritable: true },
+            { pubkey: userY, isSigner: false, isWritable: true },
+          ])
+        )
+        .signers([cranker])
+        .rpc();
+
+    before(async () => {
+      const sig = await connection.requestAirdrop(cranker.publicKey, 1_000_000_000);
+      await connection.confirmTransaction(sig);
+    });
+
+    it("Fills orders whose price has crossed and skips the rest", async () => {
+      const amountIn = new anchor.BN(1_000_000);
+      // Asks for 100x the spot price, the pool can't pay that
+      const resting = await placeOrder(new anchor.BN(1), amountIn, new anchor.BN(100_000_000));
+      // Asks for half the spot price, fills straight away
+      const crossed = await placeOrder(new anchor.BN(2), amountIn, new anchor.BN(400_000));
+
+      const userYBefore = await getAccount(connection, userY);
+      const crankerBefore = await connection.getBalance(cranker.publicKey);
+      const configBefore = await program.account.config.fetch(config);
+      await fillOrders([resting, crossed]);
+      const userYAfter = await getAccount(connection, userY);
+      const crankerAfter = await connection.getBalance(cranker.publicKey);
+      const configAfter = await program.account.config.fetch(config);
+
+      // Only the crossed order paid out, at or above its limit price
+      const filledOut = Number(userYAfter.amount - userYBefore.amount);
+      expect(filledOut).to.be.at.least(400_000);
+      expect(configAfter.reserveX.sub(configBefore.reserveX).toString()).to.equal(amountIn.toString());
+      expect(configBefore.reserveY.sub(configAfter.reserveY).toNumber()).to.equal(filledOut);
+      // The wallet pays the transaction fee, so the cranker nets exactly one crank fee
+      expect(crankerAfter - crankerBefore).to.equal(crankFee.toNumber());
+
+      expect(await connection.getAccountInfo(crossed.order)).to.be.null;
+      expect(await connection.getAccountInfo(crossed.orderVault)).to.be.null;
+      const restingOrder = await program.account.order.fetch(resting.order);
+      expect(restingOrder.amountIn.toString()).to.equal(amountIn.toString());
+      expect(restingOrder.minAmountOut.toNumber()).to.equal(100_000_000);
+      expect(restingOrder.crankFee.toString()).to.equal(crankFee.toString());
+      expect(Number((await getAccount(connection, resting.orderVault)).amount)).to.equal(amountIn.toNumber());
+
+      await program.methods
+        .cancelOrder()
+        .accountsStrict({
+          owner: wallet.publicKey,
+          config,
+          mintIn: mintX,
+          order: resting.order,
+          orderVault: resting.orderVault,
+          ownerIn: userX,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+      expect(await connection.getAccountInfo(resting.order)).to.be.null;
+      expect(await connection.getAccountInfo(resting.orderVault)).to.be.null;
+    });
+  });
+
+  describe("Closing a pool", () => {
+    const closeSeed = new anchor.BN(14);
+    const [closeConfig] = PublicKey.findProgramAddressSync(
+      [Buffer.from("config"), closeSeed.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [closeMintLp] = PublicKey.findProgramAddressSync(
+      [Buffer.from("lp"), closeConfig.toBuffer()],
+      program.programId
+    );
+    const closeVaultX = getAssociatedTokenAddressSync(mintX, closeConfig, true);
+    const closeVaultY = getAssociatedTokenAddressSync(mintY, closeConfig, true);
+    const closeUserLp = getAssociatedTokenAddressSync(closeMintLp, wallet.publicKey, false, TOKEN_2022_PROGRAM_ID);
+
+    const initialize = (authority: PublicKey | null) =>
+      program.methods
+        .initialize(closeSeed, fee, authority)
+        .accountsStrict({
+          initializer: wallet.publicKey,
+          mintX: mintX,
+          mintY: mintY,
+          mintLp: closeMintLp,
+          vaultX: closeVaultX,
+          vaultY: closeVaultY,
+          config: closeConfig,
+          lpMetadata: metadataAddress(closeMintLp),
+          metadataX: null,
+          metadataY: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          lpTokenProgram: TOKEN_2022_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          metadataProgram: TOKEN_METADATA_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        })
+        .rpc();
+
+    const closePool = (authority: Keypair) =>
+      program.methods
+        .closePool()
+        .accountsStrict({
+          authority: authority.publicKey,
+          mintX: mintX,
+          mintY: mintY,
+          config: closeConfig,
+          mintLp: closeMintLp,
+          vaultX: closeVaultX,
+          vaultY: closeVaultY,
+          authorityX: getAssociatedTokenAddressSync(mintX, authority.publicKey),
+          authorityY: getAssociatedTokenAddressSync(mintY, authority.publicKey),
+          tokenProgram: TOKEN_PROGRAM_ID,
+          lpTokenProgram: TOKEN_2022_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([authority])
+        .rpc();
+
+    const expectClosed = async () => {
+      for (const account of [closeConfig, closeMintLp, closeVaultX, closeVaultY]) {
+        expect(await connection.getAccountInfo(account)).to.be.null;
+      }
+    };
+
+    it("Closes an emptied pool and hands its dust to the authority", async () => {
+      await initialize(wallet.publicKey);
+      const liquidity = {
+        user: wallet.publicKey,
+        mintX: mintX,
+        mintY: mintY,
+        config: closeConfig,
+        mintLp: closeMintLp,
+        vaultX: closeVaultX,
+        vaultY: closeVaultY,
+        userX: userX,
+        userY: userY,
+        userLp: closeUserLp,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        lpTokenProgram: TOKEN_2022_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      };
+      await program.methods
+        .deposit(new anchor.BN(1_000_000), new anchor.BN(1_000_000), new anchor.BN(1_000_000))
+        .accountsStrict(liquidity)
+        .rpc();
+
+      // The pool cannot close while anyone still holds LP
+      try {
+        await closePool(wallet.payer);
+        expect.fail("closing a pool with LP outstanding should fail");
+      } catch (err) {
+        expect(err.error.errorCode.code).to.equal("PoolNotEmpty");
+      }
+
+      await program.methods
+        .withdraw(new anchor.BN(1_000_000), new anchor.BN(0), new anchor.BN(0))
+        .accountsStrict(liquidity)
+        .rpc();
+      await anchor.web3.sendAndConfirmTransaction(
+        connection,
+        new anchor.web3.Transaction().add(createTransferInstruction(userX, closeVaultX, wallet.publicKey, 5)),
+        [wallet.payer]
+      );
+
+      const userXBefore = await getAccount(connection, userX);
+      await closePool(wallet.payer);
+      const userXAfter = await getAccount(connection, userX);
+
+      expect(Number(userXAfter.amount - userXBefore.amount)).to.equal(5);
+      await expectClosed();
+    });
+
+    it("Re-creates the seed and lets anyone close it without an authority", async () => {
+      // The LP metadata outlives the pool and is reused, not created again
+      await initialize(null);
+      expect(await connection.getAccountInfo(metadataAddress(closeMintLp))).to.not.be.null;
+
+      const stranger = Keypair.generate();
+      await connection.confirmTransaction(
+        await connection.requestAirdrop(stranger.publicKey, anchor.web3.LAMPORTS_PER_SOL)
+      );
+      await closePool(stranger);
+      await expectClosed();
+    });
+  });
+});
@@ -3,10 +3,34 @@
 // configured from the workspace's Anchor.toml.
 
 import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { AnchorEscrowQ425 } from "../target/types/anchor_escrow_q4_25";
 
 module.exports = async function (provider: anchor.AnchorProvider) {
   // Configure client to use the provider.
   anchor.setProvider(provider);
 
-  // Add your deploy script here.
+  const program = anchor.workspace.AnchorEscrowQ425 as Program<AnchorEscrowQ425>;
+
+  // Every take reads the fee from the `config` PDA, so it has to exist before the first one.
+  // Only the upgrade authority (the deploying wallet) can create it; it starts out fee-free,
+  // with that wallet as the authority and treasury, and can be changed later with `setConfig`
+  const [config] = anchor.web3.PublicKey.findProgramAddressSync([Buffer.from("config")], program.programId);
+  if (await provider.connection.getAccountInfo(config)) {
+    return;
+  }
+  const [programData] = anchor.web3.PublicKey.findProgramAddressSync(
+    [program.programId.toBuffer()],
+    new anchor.web3.PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+  );
+  await program.methods
+    .setConfig(provider.wallet.publicKey, provider.wallet.publicKey, 0)
+    .accountsStrict({
+      authority: provider.wallet.publicKey,
+      config,
+      program: program.programId,
+      programData,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    })
+    .rpc();
 };
@@ -0,0 +1,87 @@
+use anchor_lang::error_code;
+
+#[error_code]
+pub enum EscrowError {
+    #[msg("Invalid amount.")]
+    InvalidAmount,
+    #[msg("Amount exceeds what is left in the escrow.")]
+    ExceedsRemaining,
+    #[msg("Overflow detected.")]
+    Overflow,
+    #[msg("Expiry must be in the future.")]
+    InvalidExpiry,
+    #[msg("This escrow has expired.")]
+    Expired,
+    #[msg("This escrow has not expired yet.")]
+    NotExpired,
+    #[msg("This escrow is reserved for another taker.")]
+    InvalidTaker,
+    #[msg("The escrow terms changed since the pinned revision.")]
+    RevisionMismatch,
+    #[msg("Fee is greater than 100%.")]
+    InvalidFee,
+    #[msg("Invalid config authority.")]
+    InvalidAuthority,
+    #[msg("Invalid mpl-core asset.")]
+    InvalidAsset,
+    #[msg("Collection account does not match the asset.")]
+    InvalidCollection,
+    #[msg("Asset is not owned by the expected wallet.")]
+    NotAssetOwner,
+    #[msg("Invalid bundle legs.")]
+    InvalidBundle,
+    #[msg("Account does not match the bundle leg.")]
+    InvalidBundleAccount,
+    #[msg("Escrow is not in the right state for this action.")]
+    InvalidStatus,
+    #[msg("Signer is not allowed to act on this escrow.")]
+    Unauthorized,
+    #[msg("The dispute window has closed.")]
+    DisputeWindowClosed,
+    #[msg("The dispute window is still open.")]
+    DisputeWindowOpen,
+    #[msg("Both sides of the escrow use the same mint.")]
+    SameMint,
+    #[msg("Invalid milestones.")]
+    InvalidMilestones,
+    #[msg("Preimage does not match the hash lock.")]
+    InvalidPreimage,
+    #[msg("Invalid Dutch auction parameters.")]
+    InvalidAuction,
+    #[msg("Not supported for Dutch auction escrows.")]
+    AuctionUnsupported,
+    #[msg("Not supported for bundle escrows.")]
+    BundleUnsupported,
+    #[msg("Hash locks need a named taker and a fixed price.")]
+    InvalidHashLock,
+    #[msg("Not supported for hash-locked escrows.")]
+    HashLockUnsupported,
+    #[msg("Missing or malformed ed25519 signature instruction.")]
+    InvalidSignature,
+    #[msg("Signed intent does not match this take.")]
+    InvalidIntent,
+    #[msg("Escrow account does not belong to this batch.")]
+    InvalidEscrowAccount,
+    #[msg("Total cost exceeds the maximum spend.")]
+    MaxSpendExceeded,
+    #[msg("Token A received is below the minimum.")]
+    MinReceiveNotMet,
+    #[msg("Offer book page is not open.")]
+    InvalidBookPage,
+    #[msg("Offer book page is full.")]
+    BookPageFull,
+    #[msg("Offer is below the book's minimum.")]
+    OfferTooSmall,
+    #[msg("Book accounts are passed for listed offers only.")]
+    InvalidBookAccounts,
+    #[msg("Token accounts can only be left out for a native SOL side.")]
+    InvalidNativeAccounts,
+    #[msg("Not supported for asset escrows.")]
+    AssetUnsupported,
+    #[msg("Not supported for arbitrated escrows.")]
+    ArbitrationUnsupported,
+    #[msg("The arbiter can still rule on this dispute.")]
+    RulingPending,
+    #[msg("Not supported for milestone escrows.")]
+    MilestoneUnsupported,
+}
@@ -0,0 +1,183 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::{
+    create_treasury_ata, empty_and_close_vault, errors::EscrowError, BookPage, Config,
+    CounterOffer, Escrow,
+};
+
+#[derive(Accounts)]
+pub struct AcceptCounter<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    #[account(mut)]
+    pub taker: SystemAccount<'info>,
+
+    pub mint_a: InterfaceAccount<'info, Mint>,
+    pub mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = maker,
+        associated_token::mint = mint_a,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_ata_a: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = maker,
+        associated_token::mint = mint_b,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_ata_b: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = treasury,
+    )]
+    pub config: Account<'info, Config>,
+    pub treasury: SystemAccount<'info>,
+    // Only created once there is a fee to collect, so fee-free takes skip its rent
+    #[account(mut)]
+    pub treasury_ata_b: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker,
+        has_one = mint_a,
+        has_one = mint_b,
+        seeds = [b"escrow", maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+        constraint = !escrow.is_expired(Clock::get()?.unix_timestamp) @ EscrowError::Expired,
+        constraint = !escrow.is_listed() || book_page.is_some() @ EscrowError::InvalidBookAccounts,
+    )]
+    pub escrow: Account<'info, Escrow>,
+    // Only for listed escrows, which must bring it so they come off the book
+    #[account(
+        mut,
+        seeds = [
+            b"book_page",
+            escrow.mint_a.as_ref(),
+            escrow.mint_b.as_ref(),
+            escrow.book_page.to_le_bytes().as_ref(),
+        ],
+        bump = book_page.bump,
+    )]
+    pub book_page: Option<Account<'info, BookPage>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    // The taker only gets what they countered against; any change to the escrow since voids it,
+    // and so does the escrow being closed and made again at the same address
+    #[account(
+        mut,
+        close = taker,
+        has_one = escrow,
+        has_one = taker,
+        has_one = mint_a,
+        seeds = [b"counter", escrow.key().as_ref(), taker.key().as_ref()],
+        bump = counter.bump,
+        constraint = counter.seed == escrow.seed @ EscrowError::RevisionMismatch,
+        constraint = counter.revision == escrow.revision @ EscrowError::RevisionMismatch,
+        constraint = counter.offer == escrow.offer @ EscrowError::RevisionMismatch,
+    )]
+    pub counter: Account<'info, CounterOffer>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = counter,
+        associated_token::token_program = token_program,
+    )]
+    pub counter_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> AcceptCounter<'info> {
+    // Counter vault -> treasury and maker, then close it back to the taker. The maker gets
+    // the whole rest of the vault, so a donation to it can't keep it from closing
+    pub fn deposit(&mut self) -> Result<()> {
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"counter",
+            self.counter.escrow.as_ref(),
+            self.counter.taker.as_ref(),
+            &[self.counter.bump],
+        ]];
+
+        if self.counter.fee > 0 {
+            create_treasury_ata(
+                &self.treasury_ata_b,
+                self.treasury.to_account_info(),
+                self.mint_b.to_account_info(),
+                self.maker.to_account_info(),
+                &self.token_program,
+                &self.associated_token_program,
+                &self.system_program,
+            )?;
+            let transfer_accounts = TransferChecked {
+                from: self.counter_vault.to_account_info(),
+                mint: self.mint_b.to_account_info(),
+                to: self.treasury_ata_b.to_account_info(),
+                authority: self.counter.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                transfer_accounts,
+                signer_seeds,
+            );
+            transfer_checked(cpi_ctx, self.counter.fee, self.mint_b.decimals)?;
+            self.counter_vault.reload()?;
+        }
+
+        empty_and_close_vault(
+            &self.counter_vault,
+            &self.mint_b,
+            self.maker_ata_b.to_account_info(),
+            self.taker.to_account_info(),
+            self.counter.to_account_info(),
+            self.token_program.to_account_info(),
+            signer_seeds,
+        )
+    }
+
+    // Escrow vault -> taker, then close it back to the maker
+    pub fn withdraw_and_close_vault(&mut self) -> Result<()> {
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"escrow",
+            self.maker.to_account_info().key.as_ref(),
+            &self.escrow.seed.to_le_bytes(),
+            &[self.escrow.bump],
+        ]];
+
+        empty_and_close_vault(
+            &self.vault,
+            &self.mint_a,
+            self.taker_ata_a.to_account_info(),
+            self.maker.to_account_info(),
+            self.escrow.to_account_info(),
+            self.token_program.to_account_info(),
+            signer_seeds,
+        )
+    }
+
+    // Takes the escrow off its book page, if it is listed
+    pub fn delist(&mut self) {
+        if let Some(book_page) = &mut self.book_page {
+            book_page.remove(&self.escrow.key());
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+
+use crate::{errors::EscrowError, ArbitrationStatus, Escrow};
+
+#[derive(Accounts)]
+pub struct DisputeArbitrated<'info> {
+    // Either the maker or the taker
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        constraint = escrow.arbitration_status() == Some(ArbitrationStatus::Taken) @ EscrowError::InvalidStatus,
+        constraint = escrow.maker == signer.key() || escrow.arbitration.is_some_and(|arbitration| arbitration.taker == signer.key()) @ EscrowError::Unauthorized,
+        seeds = [b"escrow", escrow.maker.as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+impl<'info> DisputeArbitrated<'info> {
+    // Freezes the trade until the arbiter decides which way the vaults go
+    pub fn dispute(&mut self) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let arbitration = self
+            .escrow
+            .arbitration
+            .as_mut()
+            .ok_or(EscrowError::InvalidStatus)?;
+        require!(
+            arbitration.dispute_window_open(now),
+            EscrowError::DisputeWindowClosed
+        );
+        arbitration.status = ArbitrationStatus::Disputed;
+        arbitration.disputed_at = now;
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    create_leg_atas, empty_and_close_leg_vaults, empty_and_close_vault, errors::EscrowError,
+    is_native, transfer_asset, unwrap_and_close_vault, BookPage, Escrow,
+};
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+#[derive(Accounts)]
+pub struct Expire<'info> {
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+    #[account(
+        constraint = maker_ata_a.is_some() || is_native(&mint_a.key()) @ EscrowError::InvalidNativeAccounts,
+    )]
+    pub mint_a: InterfaceAccount<'info, Mint>,
+    // Left out to return native SOL to the maker as lamports
+    #[account(
+        init_if_needed,
+        payer = cranker,
+        associated_token::mint = mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_ata_a: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        close = maker,
+        has_one = mint_a,
+        has_one = maker,
+        seeds = [b"escrow", maker.key().as_ref(), &escrow.seed.to_le_bytes()],
+        bump = escrow.bump,
+        constraint = escrow.is_expired(Clock::get()?.unix_timestamp) @ EscrowError::NotExpired,
+        constraint = !escrow.is_arbitration_pending() @ EscrowError::InvalidStatus,
+        constraint = !escrow.is_listed() || book_page.is_some() @ EscrowError::InvalidBookAccounts,
+    )]
+    pub escrow: Account<'info, Escrow>,
+    // Only for listed escrows, which must bring it so they come off the book
+    #[account(
+        mut,
+        seeds = [
+            b"book_page",
+            escrow.mint_a.as_ref(),
+            escrow.mint_b.as_ref(),
+            escrow.book_page.to_le_bytes().as_ref(),
+        ],
+        bump = book_page.bump,
+    )]
+    pub book_page: Option<Account<'info, BookPage>>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> Expire<'info> {
+    // Same as a refund, but anyone can call it once the offer has expired. The vault's rent
+    // goes to the cranker as a bounty, the escrow's rent back to the maker. A bundle's offered
+    // legs come in `remaining_accounts` like for a refund, their vaults' rent going to the maker.
+    // So do a sold asset's accounts, the cranker paying for its transfer back.
+    pub fn refund_and_close(
+        &mut self,
+        remaining_accounts: &'info [AccountInfo<'info>],
+    ) -> Result<()> {
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"escrow",
+            self.maker.to_account_info().key.as_ref(),
+            &self.escrow.seed.to_le_bytes(),
+            &[self.escrow.bump],
+        ]];
+
+        match &self.maker_ata_a {
+            Some(maker_ata_a) => empty_and_close_vault(
+                &self.vault,
+                &self.mint_a,
+                maker_ata_a.to_account_info(),
+                self.cranker.to_account_info(),
+                self.escrow.to_account_info(),
+                self.token_program.to_account_info(),
+                signer_seeds,
+            )?,
+            None => unwrap_and_close_vault(
+                &self.vault,
+                self.escrow.to_account_info(),
+                self.maker.to_account_info(),
+                self.cranker.to_account_info(),
+                self.token_program.to_account_info(),
+                signer_seeds,
+            )?,
+        }
+        if let Some(asset) = self.escrow.asset.filter(|asset| asset.offered) {
+            return transfer_asset(
+                &asset,
+                remaining_accounts,
+                &self.escrow.to_account_info(),
+                &self.maker.to_account_info(),
+                &self.cranker.to_account_info(),
+                &self.system_program.to_account_info(),
+                signer_seeds,
+            );
+        }
+        create_leg_atas(
+            remaining_accounts,
+            self.maker.to_account_info(),
+            self.cranker.to_account_info(),
+            &self.token_program,
+            &self.associated_token_program,
+            &self.system_program,
+        )?;
+        empty_and_close_leg_vaults(
+            &self.escrow,
+            remaining_accounts,
+            &self.maker.key(),
+            self.maker.to_account_info(),
+            &self.token_program,
+            signer_seeds,
+        )
+    }
+
+    // Takes the escrow off its book page, if it is listed
+    pub fn delist(&mut self) {
+        if let Some(book_page) = &mut self.book_page {
+            book_page.remove(&self.escrow.key());
+        }
+    }
+}
@@ -1,31 +1,57 @@
 use anchor_lang::prelude::*;
 
-use crate::Escrow;
+use crate::{
+    check_bundle_ata, errors::EscrowError, is_native, transfer_asset, validate_legs,
+    validate_milestones, wrap_into_vault, Arbitration, ArbitrationTerms, AssetLeg, BookPage,
+    BundleLeg, DutchAuction, Escrow, OfferBook,
+};
 use anchor_spl::{
-    associated_token::AssociatedToken,
+    associated_token::{create_idempotent, AssociatedToken, Create},
     token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
 };
+use mpl_core::ID as CORE_PROGRAM_ID;
+
+// Accounts passed per offered bundle leg in `remaining_accounts`: mint, maker's token account, vault
+const ACCOUNTS_PER_DEPOSITED_LEG: usize = 3;
+
+// Optional modes of a new escrow, see the matching `Escrow` fields. Left unset, the escrow is
+// a public, fixed-price offer of token A alone, listed on page 0 of the pair's book
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct MakeParams {
+    pub taker: Option<Pubkey>,
+    pub auction: Option<DutchAuction>,
+    pub page: u32,
+    pub offer_legs: Vec<BundleLeg>,
+    pub receive_legs: Vec<BundleLeg>,
+    pub hash_lock: Option<[u8; 32]>,
+    pub asset: Option<AssetLeg>,
+    pub arbitration: Option<ArbitrationTerms>,
+    pub milestones: Vec<u64>,
+    pub approver: Option<Pubkey>,
+}
 
 #[derive(Accounts)]
-#[instruction(seed: u64)]
+#[instruction(seed: u64, offer_from_a: u64, receive: u64, expires_at: i64, params: MakeParams)]
 pub struct Make<'info> {
     #[account(mut)]
     pub maker: Signer<'info>,
     #[account(
-        mint::token_program = token_program
+        mint::token_program = token_program,
+        constraint = maker_ata_a.is_some() || is_native(&mint_a.key()) @ EscrowError::InvalidNativeAccounts,
     )]
     pub mint_a: InterfaceAccount<'info, Mint>,
     #[account(
         mint::token_program = token_program
     )]
     pub mint_b: InterfaceAccount<'info, Mint>,
+    // Left out to offer native SOL straight from the maker's lamports
     #[account(
         mut,
         associated_token::mint = mint_a,
         associated_token::authority = maker,
         associated_token::token_program = token_program
     )]
-    pub maker_ata_a: InterfaceAccount<'info, TokenAccount>,
+    pub maker_ata_a: Option<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         init,
         payer = maker,
@@ -34,6 +60,28 @@ pub struct Make<'info> {
         bump
     )]
     pub escrow: Account<'info, Escrow>,
+    // Only passed for offers that go on the book, so unlisted ones never pay for its pages
+    #[account(
+        init_if_needed,
+        payer = maker,
+        seeds = [b"book", mint_a.key().as_ref(), mint_b.key().as_ref()],
+        space = OfferBook::DISCRIMINATOR.len() + OfferBook::INIT_SPACE,
+        bump
+    )]
+    pub offer_book: Option<Account<'info, OfferBook>>,
+    #[account(
+        init_if_needed,
+        payer = maker,
+        seeds = [
+            b"book_page",
+            mint_a.key().as_ref(),
+            mint_b.key().as_ref(),
+            params.page.to_le_bytes().as_ref(),
+        ],
+        space = BookPage::DISCRIMINATOR.len() + BookPage::INIT_SPACE,
+        bump
+    )]
+    pub book_page: Option<Account<'info, BookPage>>,
     #[account(
         init,
         payer = maker,
@@ -49,21 +97,251 @@ pub struct Make<'info> {
 }
 
 impl<'info> Make<'info> {
-    pub fn init_escrow(&mut self, seed: u64, receive: u64, bumps: &MakeBumps) -> Result<()> {
+    pub fn init_escrow(
+        &mut self,
+        seed: u64,
+        offer: u64,
+        receive: u64,
+        expires_at: i64,
+        taker: Option<Pubkey>,
+        bumps: &MakeBumps,
+    ) -> Result<()> {
+        require!(
+            expires_at > Clock::get()?.unix_timestamp,
+            EscrowError::InvalidExpiry
+        );
+
         self.escrow.set_inner(Escrow {
             seed,
             maker: self.maker.key(),
             mint_a: self.mint_a.key(),
             mint_b: self.mint_b.key(),
             receive,
+            offer,
+            expires_at,
+            taker,
+            revision: 0,
+            auction: None,
+            book_page: 0,
+            hash_lock: None,
+            asset: None,
+            arbitration: None,
+            offer_legs: Vec::new(),
+            receive_legs: Vec::new(),
+            milestones: Vec::new(),
+            released: 0,
+            approver: None,
             bump: bumps.escrow,
         });
         Ok(())
     }
 
+    // Turns the offer into a declining-price sale starting at `receive`
+    pub fn set_auction(&mut self, auction: DutchAuction) -> Result<()> {
+        require!(
+            auction.is_valid(self.escrow.receive),
+            EscrowError::InvalidAuction
+        );
+        self.escrow.auction = Some(auction);
+        Ok(())
+    }
+
+    // Makes the offer claimable only with the preimage of `hash`, for swaps whose other side
+    // settles on another chain. It has to be private and fixed-price so the counterparty
+    // knows exactly what they lock against
+    pub fn set_hash_lock(&mut self, hash: [u8; 32]) -> Result<()> {
+        require!(
+            self.escrow.taker.is_some() && !self.escrow.is_auction(),
+            EscrowError::InvalidHashLock
+        );
+        self.escrow.hash_lock = Some(hash);
+        Ok(())
+    }
+
+    // Turns the offer into a bundle trading `offer_legs` on top of token A for `receive_legs`
+    // on top of token B, moving each offered leg into its own vault
+    pub fn set_legs(
+        &mut self,
+        offer_legs: Vec<BundleLeg>,
+        receive_legs: Vec<BundleLeg>,
+        remaining_accounts: &'info [AccountInfo<'info>],
+    ) -> Result<()> {
+        validate_legs(&self.mint_a.key(), &offer_legs)?;
+        validate_legs(&self.mint_b.key(), &receive_legs)?;
+        require!(
+            remaining_accounts.len() == offer_legs.len() * ACCOUNTS_PER_DEPOSITED_LEG,
+            EscrowError::InvalidBundleAccount
+        );
+        self.escrow.offer_legs = offer_legs;
+        self.escrow.receive_legs = receive_legs;
+        require!(
+            !self.escrow.is_bundle() || !self.escrow.is_auction(),
+            EscrowError::BundleUnsupported
+        );
+
+        let token_program = self.token_program.key();
+        for (leg, accounts) in self
+            .escrow
+            .offer_legs
+            .iter()
+            .zip(remaining_accounts.chunks_exact(ACCOUNTS_PER_DEPOSITED_LEG))
+        {
+            let [mint, maker_ata, vault] = accounts else {
+                unreachable!();
+            };
+            require_keys_eq!(mint.key(), leg.mint, EscrowError::InvalidBundleAccount);
+            let mint_account = InterfaceAccount::<Mint>::try_from(mint)?;
+            check_bundle_ata(maker_ata, &leg.mint, &self.maker.key(), &token_program)?;
+            check_bundle_ata(vault, &leg.mint, &self.escrow.key(), &token_program)?;
+
+            let create_accounts = Create {
+                payer: self.maker.to_account_info(),
+                associated_token: vault.clone(),
+                authority: self.escrow.to_account_info(),
+                mint: mint.clone(),
+                system_program: self.system_program.to_account_info(),
+                token_program: self.token_program.to_account_info(),
+            };
+            create_idempotent(CpiContext::new(
+                self.associated_token_program.to_account_info(),
+                create_accounts,
+            ))?;
+
+            let transfer_accounts = TransferChecked {
+                from: maker_ata.clone(),
+                mint: mint.clone(),
+                to: vault.clone(),
+                authority: self.maker.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), transfer_accounts);
+            transfer_checked(cpi_ctx, leg.amount, mint_account.decimals)?;
+        }
+        Ok(())
+    }
+
+    // Adds an mpl-core asset on top of the token sides, which may then be left at zero. A sold
+    // asset moves into the escrow's custody now, a bid-for one is paid to the maker on take.
+    // Its accounts take the place of a bundle's legs in `remaining_accounts`, so an escrow
+    // can't be both
+    pub fn set_asset(
+        &mut self,
+        asset: AssetLeg,
+        params: &MakeParams,
+        remaining_accounts: &'info [AccountInfo<'info>],
+    ) -> Result<()> {
+        require!(
+            params.offer_legs.is_empty() && params.receive_legs.is_empty(),
+            EscrowError::BundleUnsupported
+        );
+        self.escrow.asset = Some(asset);
+
+        if !asset.offered {
+            // Ownership is only checked on take, the asset may change hands until then
+            let [account, ..] = remaining_accounts else {
+                return err!(EscrowError::InvalidAsset);
+            };
+            require_keys_eq!(account.key(), asset.address, EscrowError::InvalidAsset);
+            require_keys_eq!(*account.owner, CORE_PROGRAM_ID, EscrowError::InvalidAsset);
+            return Ok(());
+        }
+        transfer_asset(
+            &asset,
+            remaining_accounts,
+            &self.maker.to_account_info(),
+            &self.escrow.to_account_info(),
+            &self.maker.to_account_info(),
+            &self.system_program.to_account_info(),
+            &[],
+        )
+    }
+
+    // Holds the taker's payment next to the offer until the maker confirms delivery, with
+    // `arbiter` settling disputes. Only plain fixed-price pairs, since the trade is taken and
+    // settled through its own instructions
+    pub fn set_arbitration(&mut self, terms: ArbitrationTerms) -> Result<()> {
+        require!(terms.dispute_window > 0, EscrowError::InvalidExpiry);
+        require_keys_neq!(terms.arbiter, self.maker.key(), EscrowError::Unauthorized);
+        require!(
+            !self.escrow.is_auction()
+                && !self.escrow.is_hash_locked()
+                && !self.escrow.is_bundle()
+                && !self.escrow.is_asset(),
+            EscrowError::ArbitrationUnsupported
+        );
+        self.escrow.arbitration = Some(Arbitration::new(terms));
+        Ok(())
+    }
+
+    // Pays token A out to the named taker in `milestones` tranches, each signed off by the
+    // maker or `approver`, instead of trading it for token B
+    pub fn set_milestones(&mut self, milestones: Vec<u64>, approver: Option<Pubkey>) -> Result<()> {
+        if milestones.is_empty() {
+            require!(approver.is_none(), EscrowError::InvalidMilestones);
+            return Ok(());
+        }
+        validate_milestones(self.escrow.offer, &milestones)?;
+        require!(self.escrow.taker.is_some(), EscrowError::InvalidMilestones);
+        require!(
+            !self.escrow.is_auction()
+                && !self.escrow.is_hash_locked()
+                && !self.escrow.is_bundle()
+                && !self.escrow.is_asset()
+                && !self.escrow.is_arbitrated(),
+            EscrowError::MilestoneUnsupported
+        );
+        self.escrow.milestones = milestones;
+        self.escrow.approver = approver;
+        Ok(())
+    }
+
+    // Lists the offer on `page`, opening the pair's book and the page on first use
+    pub fn list_offer(&mut self, page: u32, bumps: &MakeBumps) -> Result<()> {
+        if !self.escrow.is_listed() {
+            require!(
+                self.offer_book.is_none() && self.book_page.is_none(),
+                EscrowError::InvalidBookAccounts
+            );
+            return Ok(());
+        }
+        let (Some(offer_book), Some(book_page)) = (&mut self.offer_book, &mut self.book_page)
+        else {
+            return err!(EscrowError::InvalidBookAccounts);
+        };
+
+        if offer_book.mint_a == Pubkey::default() {
+            offer_book.mint_a = self.mint_a.key();
+            offer_book.mint_b = self.mint_b.key();
+            offer_book.bump = bumps.offer_book.ok_or(EscrowError::InvalidBookAccounts)?;
+        }
+        if book_page.mint_a == Pubkey::default() {
+            offer_book.open_page(page)?;
+            book_page.mint_a = self.mint_a.key();
+            book_page.mint_b = self.mint_b.key();
+            book_page.page = page;
+            book_page.bump = bumps.book_page.ok_or(EscrowError::InvalidBookAccounts)?;
+        }
+        require!(
+            self.escrow.offer >= offer_book.min_offer,
+            EscrowError::OfferTooSmall
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        self.escrow.book_page = page;
+        book_page.list(self.escrow.key(), &self.escrow, now)
+    }
+
     pub fn deposit(&mut self, deposit: u64) -> Result<()> {
+        let Some(maker_ata_a) = &self.maker_ata_a else {
+            return wrap_into_vault(
+                &self.vault,
+                self.maker.to_account_info(),
+                deposit,
+                self.token_program.to_account_info(),
+                self.system_program.to_account_info(),
+            );
+        };
         let tranfer_accounts = TransferChecked {
-            from: self.maker_ata_a.to_account_info(),
+            from: maker_ata_a.to_account_info(),
             mint: self.mint_a.to_account_info(),
             to: self.vault.to_account_info(),
             authority: self.maker.to_account_info(),
@@ -0,0 +1,96 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::{errors::EscrowError, Config, CounterOffer, Escrow};
+
+#[derive(Accounts)]
+pub struct MakeCounter<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+    pub mint_b: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_ata_b: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        has_one = mint_b,
+        seeds = [b"escrow", escrow.maker.as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+        constraint = !escrow.is_expired(Clock::get()?.unix_timestamp) @ EscrowError::Expired,
+        constraint = escrow.can_be_taken_by(&taker.key()) @ EscrowError::InvalidTaker,
+        constraint = !escrow.is_bundle() @ EscrowError::BundleUnsupported,
+        constraint = !escrow.is_asset() @ EscrowError::AssetUnsupported,
+        constraint = !escrow.is_arbitrated() @ EscrowError::ArbitrationUnsupported,
+        constraint = !escrow.is_milestone() @ EscrowError::MilestoneUnsupported,
+        constraint = !escrow.is_hash_locked() @ EscrowError::HashLockUnsupported,
+    )]
+    pub escrow: Account<'info, Escrow>,
+    #[account(
+        init,
+        payer = taker,
+        seeds = [b"counter", escrow.key().as_ref(), taker.key().as_ref()],
+        space = CounterOffer::DISCRIMINATOR.len() + CounterOffer::INIT_SPACE,
+        bump
+    )]
+    pub counter: Account<'info, CounterOffer>,
+    #[account(
+        init,
+        payer = taker,
+        associated_token::mint = mint_b,
+        associated_token::authority = counter,
+        associated_token::token_program = token_program,
+    )]
+    pub counter_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MakeCounter<'info> {
+    pub fn init_counter(&mut self, receive: u64, bumps: &MakeCounterBumps) -> Result<()> {
+        require!(receive > 0, EscrowError::InvalidAmount);
+
+        self.counter.set_inner(CounterOffer {
+            escrow: self.escrow.key(),
+            taker: self.taker.key(),
+            mint_a: self.escrow.mint_a,
+            seed: self.escrow.seed,
+            offer: self.escrow.offer,
+            revision: self.escrow.revision,
+            receive,
+            fee: self.config.fee_for(receive),
+            bump: bumps.counter,
+        });
+        Ok(())
+    }
+
+    // The proposed payment and its fee wait in the counter's vault until accepted or withdrawn
+    pub fn deposit(&mut self) -> Result<()> {
+        let amount = self
+            .counter
+            .receive
+            .checked_add(self.counter.fee)
+            .ok_or(EscrowError::Overflow)?;
+
+        let transfer_accounts = TransferChecked {
+            from: self.taker_ata_b.to_account_info(),
+            mint: self.mint_b.to_account_info(),
+            to: self.counter_vault.to_account_info(),
+            authority: self.taker.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), transfer_accounts);
+        transfer_checked(cpi_ctx, amount, self.mint_b.decimals)
+    }
+}
@@ -1,8 +1,53 @@
+pub mod accept_counter;
+pub use accept_counter::*;
+
+pub mod dispute_arbitrated;
+pub use dispute_arbitrated::*;
+
+pub mod expire;
+pub use expire::*;
+
 pub mod make;
 pub use make::*;
 
+pub mod make_counter;
+pub use make_counter::*;
+
 pub mod refund;
 pub use refund::*;
 
+pub mod release_milestone;
+pub use release_milestone::*;
+
+pub mod set_book_min_offer;
+pub use set_book_min_offer::*;
+
+pub mod set_config;
+pub use set_config::*;
+
+pub mod settle_arbitrated;
+pub use settle_arbitrated::*;
+
 pub mod take;
 pub use take::*;
+
+pub mod take_arbitrated;
+pub use take_arbitrated::*;
+
+pub mod take_many;
+pub use take_many::*;
+
+pub mod take_partial;
+pub use take_partial::*;
+
+pub mod take_with_intent;
+pub use take_with_intent::*;
+
+pub mod update_offer;
+pub use update_offer::*;
+
+pub mod vault;
+pub use vault::*;
+
+pub mod withdraw_counter;
+pub use withdraw_counter::*;
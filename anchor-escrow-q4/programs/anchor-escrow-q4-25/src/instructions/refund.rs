@@ -1,35 +1,54 @@
 use anchor_lang::prelude::*;
 
-use crate::Escrow;
+use crate::{
+    empty_and_close_leg_vaults, empty_and_close_vault, errors::EscrowError, is_native,
+    transfer_asset, unwrap_and_close_vault, BookPage, Escrow,
+};
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token_interface::{
-        close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface,
-        TransferChecked,
-    },
+    token_interface::{Mint, TokenAccount, TokenInterface},
 };
 
 #[derive(Accounts)]
 pub struct Refund<'info> {
     #[account(mut)]
     pub maker: Signer<'info>,
+    #[account(
+        constraint = maker_ata_a.is_some() || is_native(&mint_a.key()) @ EscrowError::InvalidNativeAccounts,
+    )]
     pub mint_a: InterfaceAccount<'info, Mint>,
+    // Left out to get native SOL back as lamports
     #[account(
         mut,
         associated_token::mint = mint_a,
         associated_token::authority = maker,
         associated_token::token_program = token_program,
     )]
-    pub maker_ata_a: InterfaceAccount<'info, TokenAccount>,
+    pub maker_ata_a: Option<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         close = maker,
         has_one = mint_a,
         has_one = maker,
         seeds = [b"escrow", maker.key().as_ref(), &escrow.seed.to_le_bytes()],
-        bump = escrow.bump
+        bump = escrow.bump,
+        constraint = escrow.can_be_refunded(Clock::get()?.unix_timestamp) @ EscrowError::NotExpired,
+        constraint = !escrow.is_arbitration_pending() @ EscrowError::InvalidStatus,
+        constraint = !escrow.is_listed() || book_page.is_some() @ EscrowError::InvalidBookAccounts,
     )]
     pub escrow: Account<'info, Escrow>,
+    // Only for listed escrows, which must bring it so they come off the book
+    #[account(
+        mut,
+        seeds = [
+            b"book_page",
+            escrow.mint_a.as_ref(),
+            escrow.mint_b.as_ref(),
+            escrow.book_page.to_le_bytes().as_ref(),
+        ],
+        bump = book_page.bump,
+    )]
+    pub book_page: Option<Account<'info, BookPage>>,
 
     #[account(
         mut,
@@ -45,7 +64,12 @@ pub struct Refund<'info> {
 }
 
 impl<'info> Refund<'info> {
-    pub fn refund_and_close_vault(&mut self) -> Result<()> {
+    // `remaining_accounts` carries a bundle's offered legs: mint, vault, maker's token account
+    // each. For a sold asset it carries the asset's accounts instead
+    pub fn refund_and_close_vault(
+        &mut self,
+        remaining_accounts: &'info [AccountInfo<'info>],
+    ) -> Result<()> {
         let signer_seeds: &[&[&[u8]]] = &[&[
             b"escrow",
             self.maker.to_account_info().key.as_ref(),
@@ -53,33 +77,50 @@ impl<'info> Refund<'info> {
             &[self.escrow.bump],
         ]];
 
-        let transfer_accounts = TransferChecked {
-            from: self.vault.to_account_info(),
-            mint: self.mint_a.to_account_info(),
-            to: self.maker_ata_a.to_account_info(),
-            authority: self.escrow.to_account_info(),
-        };
-
-        let tranfer_cpi_ctx = CpiContext::new_with_signer(
-            self.token_program.to_account_info(),
-            transfer_accounts,
-            signer_seeds,
-        );
-
-        transfer_checked(tranfer_cpi_ctx, self.vault.amount, self.mint_a.decimals)?;
-
-        let close_accounts = CloseAccount {
-            account: self.vault.to_account_info(),
-            destination: self.maker.to_account_info(),
-            authority: self.escrow.to_account_info(),
-        };
-
-        let close_cpi_ctx = CpiContext::new_with_signer(
-            self.token_program.to_account_info(),
-            close_accounts,
+        match &self.maker_ata_a {
+            Some(maker_ata_a) => empty_and_close_vault(
+                &self.vault,
+                &self.mint_a,
+                maker_ata_a.to_account_info(),
+                self.maker.to_account_info(),
+                self.escrow.to_account_info(),
+                self.token_program.to_account_info(),
+                signer_seeds,
+            )?,
+            None => unwrap_and_close_vault(
+                &self.vault,
+                self.escrow.to_account_info(),
+                self.maker.to_account_info(),
+                self.maker.to_account_info(),
+                self.token_program.to_account_info(),
+                signer_seeds,
+            )?,
+        }
+        if let Some(asset) = self.escrow.asset.filter(|asset| asset.offered) {
+            return transfer_asset(
+                &asset,
+                remaining_accounts,
+                &self.escrow.to_account_info(),
+                &self.maker.to_account_info(),
+                &self.maker.to_account_info(),
+                &self.system_program.to_account_info(),
+                signer_seeds,
+            );
+        }
+        empty_and_close_leg_vaults(
+            &self.escrow,
+            remaining_accounts,
+            &self.maker.key(),
+            self.maker.to_account_info(),
+            &self.token_program,
             signer_seeds,
-        );
+        )
+    }
 
-        close_account(close_cpi_ctx)
+    // Takes the escrow off its book page, if it is listed
+    pub fn delist(&mut self) {
+        if let Some(book_page) = &mut self.book_page {
+            book_page.remove(&self.escrow.key());
+        }
     }
 }
@@ -0,0 +1,116 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::{empty_and_close_vault, errors::EscrowError, Escrow};
+
+#[derive(Accounts)]
+pub struct ReleaseMilestone<'info> {
+    // The maker or the escrow's approver
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+    // The escrow's named taker
+    pub beneficiary: SystemAccount<'info>,
+    pub mint_a: InterfaceAccount<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        associated_token::mint = mint_a,
+        associated_token::authority = beneficiary,
+        associated_token::token_program = token_program,
+    )]
+    pub beneficiary_ata_a: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        has_one = maker,
+        has_one = mint_a,
+        seeds = [b"escrow", maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.is_milestone() @ EscrowError::InvalidMilestones,
+        constraint = escrow.taker == Some(beneficiary.key()) @ EscrowError::InvalidTaker,
+        constraint = escrow.can_approve(&signer.key()) @ EscrowError::Unauthorized,
+    )]
+    pub escrow: Account<'info, Escrow>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ReleaseMilestone<'info> {
+    // Pays the next tranche out of the vault. Once paid it is the beneficiary's, a later
+    // refund only returns the tranches still left
+    pub fn release(&mut self) -> Result<()> {
+        let tranche = self
+            .escrow
+            .next_milestone()
+            .ok_or(EscrowError::InvalidMilestones)?;
+        self.escrow.offer = self
+            .escrow
+            .offer
+            .checked_sub(tranche)
+            .ok_or(EscrowError::Overflow)?;
+        self.escrow.released += 1;
+        // The last tranche is paid by close_if_done, which empties the vault
+        if self.escrow.next_milestone().is_none() {
+            return Ok(());
+        }
+
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"escrow",
+            self.maker.to_account_info().key.as_ref(),
+            &self.escrow.seed.to_le_bytes(),
+            &[self.escrow.bump],
+        ]];
+
+        let transfer_accounts = TransferChecked {
+            from: self.vault.to_account_info(),
+            mint: self.mint_a.to_account_info(),
+            to: self.beneficiary_ata_a.to_account_info(),
+            authority: self.escrow.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            transfer_accounts,
+            signer_seeds,
+        );
+        transfer_checked(cpi_ctx, tranche, self.mint_a.decimals)
+    }
+
+    // The last tranche empties the vault, so a donation cannot keep it from closing, and closes
+    // it and the escrow, returning rent to the maker who paid it
+    pub fn close_if_done(&mut self) -> Result<()> {
+        if self.escrow.next_milestone().is_some() {
+            return Ok(());
+        }
+
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"escrow",
+            self.maker.to_account_info().key.as_ref(),
+            &self.escrow.seed.to_le_bytes(),
+            &[self.escrow.bump],
+        ]];
+
+        empty_and_close_vault(
+            &self.vault,
+            &self.mint_a,
+            self.beneficiary_ata_a.to_account_info(),
+            self.maker.to_account_info(),
+            self.escrow.to_account_info(),
+            self.token_program.to_account_info(),
+            signer_seeds,
+        )?;
+        self.escrow.close(self.maker.to_account_info())
+    }
+}
@@ -0,0 +1,40 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+
+use crate::{errors::EscrowError, Config, OfferBook};
+
+#[derive(Accounts)]
+pub struct SetBookMinOffer<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = authority @ EscrowError::InvalidAuthority,
+    )]
+    pub config: Account<'info, Config>,
+    pub mint_a: InterfaceAccount<'info, Mint>,
+    pub mint_b: InterfaceAccount<'info, Mint>,
+    // Can be set before the first make for the pair, so the book may not exist yet
+    #[account(
+        init_if_needed,
+        payer = authority,
+        seeds = [b"book", mint_a.key().as_ref(), mint_b.key().as_ref()],
+        space = OfferBook::DISCRIMINATOR.len() + OfferBook::INIT_SPACE,
+        bump
+    )]
+    pub offer_book: Account<'info, OfferBook>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> SetBookMinOffer<'info> {
+    pub fn set_min_offer(&mut self, min_offer: u64, bumps: &SetBookMinOfferBumps) -> Result<()> {
+        if self.offer_book.mint_a == Pubkey::default() {
+            self.offer_book.mint_a = self.mint_a.key();
+            self.offer_book.mint_b = self.mint_b.key();
+            self.offer_book.bump = bumps.offer_book;
+        }
+        self.offer_book.min_offer = min_offer;
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+use anchor_lang::prelude::*;
+
+use crate::{errors::EscrowError, program::AnchorEscrowQ425, Config};
+
+#[derive(Accounts)]
+pub struct SetConfig<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        seeds = [b"config"],
+        space = Config::DISCRIMINATOR.len() + Config::INIT_SPACE,
+        bump
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        constraint = program.programdata_address()? == Some(program_data.key())
+    )]
+    pub program: Program<'info, AnchorEscrowQ425>,
+    // Only the upgrade authority can create the config; after that its own authority takes over
+    #[account(
+        constraint = config.authority == authority.key()
+            || (config.authority == Pubkey::default()
+                && program_data.upgrade_authority_address == Some(authority.key()))
+            @ EscrowError::InvalidAuthority
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> SetConfig<'info> {
+    pub fn set_config(
+        &mut self,
+        authority: Pubkey,
+        treasury: Pubkey,
+        fee_bps: u16,
+        bumps: &SetConfigBumps,
+    ) -> Result<()> {
+        require!(fee_bps <= 10_000, EscrowError::InvalidFee);
+
+        self.config.set_inner(Config {
+            authority,
+            treasury,
+            fee_bps,
+            bump: bumps.config,
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::{
+    create_treasury_ata, empty_and_close_vault, errors::EscrowError, Arbitration,
+    ArbitrationStatus, Config, Escrow,
+};
+
+#[derive(Accounts)]
+pub struct SettleArbitrated<'info> {
+    // Maker confirming delivery, taker after an undisputed window, the arbiter, or either side
+    // once the arbiter's ruling is overdue
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+    #[account(mut)]
+    pub taker: SystemAccount<'info>,
+    pub mint_a: InterfaceAccount<'info, Mint>,
+    pub mint_b: InterfaceAccount<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        associated_token::mint = mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_ata_a: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        associated_token::mint = mint_b,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_ata_b: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        associated_token::mint = mint_a,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_ata_a: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_ata_b: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = treasury,
+    )]
+    pub config: Account<'info, Config>,
+    pub treasury: SystemAccount<'info>,
+    // Only created once there is a fee to collect, so fee-free takes skip its rent
+    #[account(mut)]
+    pub treasury_ata_b: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker,
+        has_one = mint_a,
+        has_one = mint_b,
+        seeds = [b"escrow", maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.arbitration.is_some_and(|arbitration| arbitration.taker == taker.key()) @ EscrowError::Unauthorized,
+    )]
+    pub escrow: Account<'info, Escrow>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_b: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> SettleArbitrated<'info> {
+    // The maker confirms delivery, or the taker settles once the window passed without a dispute
+    pub fn confirm(&mut self) -> Result<()> {
+        let arbitration = self.arbitration(ArbitrationStatus::Taken)?;
+        let signer = self.signer.key();
+        if signer != self.escrow.maker {
+            require_keys_eq!(signer, arbitration.taker, EscrowError::Unauthorized);
+            let now = Clock::get()?.unix_timestamp;
+            require!(
+                !arbitration.dispute_window_open(now),
+                EscrowError::DisputeWindowOpen
+            );
+        }
+        self.settle(true)
+    }
+
+    // The arbiter either completes the trade or unwinds it, sending each deposit back. If the
+    // arbiter never rules, either side can unwind it once the ruling is overdue
+    pub fn resolve(&mut self, complete_trade: bool) -> Result<()> {
+        let arbitration = self.arbitration(ArbitrationStatus::Disputed)?;
+        let signer = self.signer.key();
+        if signer != arbitration.arbiter {
+            require!(
+                signer == self.escrow.maker || signer == arbitration.taker,
+                EscrowError::Unauthorized
+            );
+            require!(!complete_trade, EscrowError::Unauthorized);
+            let now = Clock::get()?.unix_timestamp;
+            require!(arbitration.ruling_overdue(now), EscrowError::RulingPending);
+        }
+        self.settle(complete_trade)
+    }
+
+    fn arbitration(&self, status: ArbitrationStatus) -> Result<Arbitration> {
+        match self.escrow.arbitration {
+            Some(arbitration) if arbitration.status == status => Ok(arbitration),
+            _ => err!(EscrowError::InvalidStatus),
+        }
+    }
+
+    pub fn settle(&mut self, complete_trade: bool) -> Result<()> {
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"escrow",
+            self.maker.to_account_info().key.as_ref(),
+            &self.escrow.seed.to_le_bytes(),
+            &[self.escrow.bump],
+        ]];
+
+        // The fee is only earned once the trade completes, otherwise the taker gets it back
+        let fee = self
+            .escrow
+            .arbitration
+            .map_or(0, |arbitration| arbitration.fee);
+        if complete_trade && fee > 0 {
+            create_treasury_ata(
+                &self.treasury_ata_b,
+                self.treasury.to_account_info(),
+                self.mint_b.to_account_info(),
+                self.signer.to_account_info(),
+                &self.token_program,
+                &self.associated_token_program,
+                &self.system_program,
+            )?;
+            let transfer_accounts = TransferChecked {
+                from: self.vault_b.to_account_info(),
+                mint: self.mint_b.to_account_info(),
+                to: self.treasury_ata_b.to_account_info(),
+                authority: self.escrow.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                transfer_accounts,
+                signer_seeds,
+            );
+            transfer_checked(cpi_ctx, fee, self.mint_b.decimals)?;
+            self.vault_b.reload()?;
+        }
+
+        let (to_a, to_b) = match complete_trade {
+            true => (&self.taker_ata_a, &self.maker_ata_b),
+            false => (&self.maker_ata_a, &self.taker_ata_b),
+        };
+
+        // Each vault's rent goes back to whoever opened it
+        for (vault, mint, to, rent_payer) in [
+            (&self.vault, &self.mint_a, to_a, &self.maker),
+            (&self.vault_b, &self.mint_b, to_b, &self.taker),
+        ] {
+            empty_and_close_vault(
+                vault,
+                mint,
+                to.to_account_info(),
+                rent_payer.to_account_info(),
+                self.escrow.to_account_info(),
+                self.token_program.to_account_info(),
+                signer_seeds,
+            )?;
+        }
+        Ok(())
+    }
+}
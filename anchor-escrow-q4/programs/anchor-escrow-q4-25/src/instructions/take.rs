@@ -1,22 +1,21 @@
-#![allow(warnings)]
-
-use anchor_lang::prelude::*;
+use anchor_lang::{prelude::*, system_program};
 use anchor_spl::{
-    associated_token::AssociatedToken,
-    token_interface::{
-        transfer_checked,
-        Mint,
-        TokenAccount,
-        TokenInterface,
-        TransferChecked,
-        CloseAccount,
-        close_account,
-    },
+    associated_token::{create_idempotent, AssociatedToken, Create},
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::{
+    check_bundle_ata, create_leg_atas, create_treasury_ata, empty_and_close_leg_vaults,
+    empty_and_close_vault, errors::EscrowError, is_native, transfer_asset, unwrap_and_close_vault,
+    AssetLeg, BookPage, Config, Escrow,
 };
 
-use crate::Escrow;
+// Accounts passed per requested bundle leg in `remaining_accounts`: mint, taker's token account,
+// maker's token account, treasury's token account. The offered legs' accounts follow them
+const ACCOUNTS_PER_RECEIVE_LEG: usize = 4;
 
 #[derive(Accounts)]
+#[instruction(revision: Option<u32>, preimage: Option<Vec<u8>>)]
 pub struct Take<'info> {
     #[account(mut)]
     pub taker: Signer<'info>,
@@ -24,45 +23,89 @@ pub struct Take<'info> {
     #[account(mut)]
     pub maker: SystemAccount<'info>,
 
+    #[account(
+        constraint = taker_ata_a.is_some() || is_native(&mint_a.key()) @ EscrowError::InvalidNativeAccounts,
+    )]
     pub mint_a: InterfaceAccount<'info, Mint>,
+    #[account(
+        constraint = taker_ata_b.is_some() || is_native(&mint_b.key()) @ EscrowError::InvalidNativeAccounts,
+        constraint = taker_ata_b.is_some() == maker_ata_b.is_some() @ EscrowError::InvalidNativeAccounts,
+    )]
     pub mint_b: InterfaceAccount<'info, Mint>,
 
+    // Left out to be paid a native SOL offer in lamports
     #[account(
         init_if_needed,
         payer = taker,
         associated_token::mint = mint_a,
-        associated_token::authority = taker
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
     )]
-    pub taker_ata_a: InterfaceAccount<'info, TokenAccount>,
+    pub taker_ata_a: Option<InterfaceAccount<'info, TokenAccount>>,
 
+    // Both left out to pay a native SOL price in lamports
     #[account(
         mut,
         associated_token::mint = mint_b,
         associated_token::authority = taker,
+        associated_token::token_program = token_program,
     )]
-    pub taker_ata_b: InterfaceAccount<'info, TokenAccount>,
-
+    pub taker_ata_b: Option<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         init_if_needed,
         payer = taker,
         associated_token::mint = mint_b,
-        associated_token::authority = maker
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_ata_b: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = treasury,
     )]
-    pub maker_ata_b: InterfaceAccount<'info, TokenAccount>,
+    pub config: Account<'info, Config>,
+    // Writable for fees paid in lamports
+    #[account(mut)]
+    pub treasury: SystemAccount<'info>,
+    // Only created once there is a fee to collect, so fee-free takes skip its rent
+    #[account(mut)]
+    pub treasury_ata_b: UncheckedAccount<'info>,
     #[account(
         mut,
         close = maker,
         has_one = maker,
         has_one = mint_a,
-        seeds = [b"escrow", maker.key().as_ref(), &escrow.seed.to_le_bytes().as_ref()],
+        has_one = mint_b,
+        seeds = [b"escrow", maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
         bump = escrow.bump,
+        constraint = !escrow.is_expired(Clock::get()?.unix_timestamp) @ EscrowError::Expired,
+        constraint = escrow.can_be_taken_by(&taker.key()) @ EscrowError::InvalidTaker,
+        constraint = escrow.matches_revision(revision) @ EscrowError::RevisionMismatch,
+        constraint = escrow.unlocks_with(preimage.as_deref()) @ EscrowError::InvalidPreimage,
+        constraint = !escrow.is_arbitrated() @ EscrowError::ArbitrationUnsupported,
+        constraint = !escrow.is_milestone() @ EscrowError::MilestoneUnsupported,
+        constraint = !escrow.is_listed() || book_page.is_some() @ EscrowError::InvalidBookAccounts,
     )]
     pub escrow: Account<'info, Escrow>,
+    // Only for listed escrows, which must bring it so they come off the book
+    #[account(
+        mut,
+        seeds = [
+            b"book_page",
+            escrow.mint_a.as_ref(),
+            escrow.mint_b.as_ref(),
+            escrow.book_page.to_le_bytes().as_ref(),
+        ],
+        bump = book_page.bump,
+    )]
+    pub book_page: Option<Account<'info, BookPage>>,
 
     #[account(
         mut,
         associated_token::mint = mint_a,
         associated_token::authority = escrow,
+        associated_token::token_program = token_program,
     )]
     pub vault: InterfaceAccount<'info, TokenAccount>,
     pub system_program: Program<'info, System>,
@@ -72,51 +115,237 @@ pub struct Take<'info> {
 
 impl<'info> Take<'info> {
     pub fn deposit(&mut self, amount: u64) -> Result<()> {
+        let (Some(taker_ata_b), Some(maker_ata_b)) = (&self.taker_ata_b, &self.maker_ata_b) else {
+            return self.deposit_lamports(amount);
+        };
         let cpi_program = self.token_program.to_account_info();
         let transfer_accounts = TransferChecked {
             authority: self.taker.to_account_info(),
-            from: self.taker_ata_b.to_account_info(),
-            to: self.maker_ata_b.to_account_info(),
+            from: taker_ata_b.to_account_info(),
+            to: maker_ata_b.to_account_info(),
             mint: self.mint_b.to_account_info(),
         };
         let cpi_ctx = CpiContext::new(cpi_program, transfer_accounts);
-        transfer_checked(cpi_ctx, amount, self.mint_b.decimals)
+        transfer_checked(cpi_ctx, amount, self.mint_b.decimals)?;
+        self.pay_fee(taker_ata_b.to_account_info(), amount)
+    }
+
+    // A native SOL price paid from the taker's lamports, the fee straight to the treasury wallet
+    fn deposit_lamports(&self, amount: u64) -> Result<()> {
+        let transfer_accounts = system_program::Transfer {
+            from: self.taker.to_account_info(),
+            to: self.maker.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.system_program.to_account_info(), transfer_accounts);
+        system_program::transfer(cpi_ctx, amount)?;
+
+        let transfer_accounts = system_program::Transfer {
+            from: self.taker.to_account_info(),
+            to: self.treasury.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.system_program.to_account_info(), transfer_accounts);
+        self.config.pay_fee_in_sol(cpi_ctx, amount)
+    }
+
+    pub fn pay_fee(&self, from: AccountInfo<'info>, amount: u64) -> Result<()> {
+        if self.config.fee_for(amount) == 0 {
+            return Ok(());
+        }
+        create_treasury_ata(
+            &self.treasury_ata_b,
+            self.treasury.to_account_info(),
+            self.mint_b.to_account_info(),
+            self.taker.to_account_info(),
+            &self.token_program,
+            &self.associated_token_program,
+            &self.system_program,
+        )?;
+        let transfer_accounts = TransferChecked {
+            from,
+            mint: self.mint_b.to_account_info(),
+            to: self.treasury_ata_b.to_account_info(),
+            authority: self.taker.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), transfer_accounts);
+        self.config.pay_fee(cpi_ctx, amount, self.mint_b.decimals)
     }
 
+    // Vault -> taker, its rent included. Native SOL comes out as lamports when the taker has
+    // no token account for it
     pub fn withdraw_and_close_vault(&mut self) -> Result<()> {
-        let seeds = &[
+        let signer_seeds: &[&[&[u8]]] = &[&[
             b"escrow",
             self.maker.to_account_info().key.as_ref(),
-            &self.escrow.seed.to_le_bytes()[..],
+            &self.escrow.seed.to_le_bytes(),
             &[self.escrow.bump],
-        ];
-        let signer_seeds = &[&seeds[..]];
-
-        let cpi_program = self.token_program.to_account_info();
+        ]];
 
-        let cpi_accounts = TransferChecked {
-            from: self.vault.to_account_info(),
-            to: self.taker_ata_a.to_account_info(),
-            authority: self.escrow.to_account_info(),
-            mint: self.mint_a.to_account_info(),
+        let Some(taker_ata_a) = &self.taker_ata_a else {
+            return unwrap_and_close_vault(
+                &self.vault,
+                self.escrow.to_account_info(),
+                self.taker.to_account_info(),
+                self.taker.to_account_info(),
+                self.token_program.to_account_info(),
+                signer_seeds,
+            );
         };
+        empty_and_close_vault(
+            &self.vault,
+            &self.mint_a,
+            taker_ata_a.to_account_info(),
+            self.taker.to_account_info(),
+            self.escrow.to_account_info(),
+            self.token_program.to_account_info(),
+            signer_seeds,
+        )
+    }
 
-        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+    // Trades a bundle's extra legs alongside token A and B, so the swap is all or nothing
+    pub fn take_legs(&mut self, remaining_accounts: &'info [AccountInfo<'info>]) -> Result<()> {
+        let receive_len = self.escrow.receive_legs.len() * ACCOUNTS_PER_RECEIVE_LEG;
+        require!(
+            remaining_accounts.len() >= receive_len,
+            EscrowError::InvalidBundleAccount
+        );
+        let (receive_accounts, offer_accounts) = remaining_accounts.split_at(receive_len);
 
-        transfer_checked(cpi_ctx, self.vault.amount, self.mint_a.decimals)?;
+        self.deposit_legs(receive_accounts)?;
 
-        let close_accounts = CloseAccount {
-            account: self.vault.to_account_info(),
-            destination: self.taker.to_account_info(),
-            authority: self.escrow.to_account_info(),
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"escrow",
+            self.maker.to_account_info().key.as_ref(),
+            &self.escrow.seed.to_le_bytes(),
+            &[self.escrow.bump],
+        ]];
+
+        create_leg_atas(
+            offer_accounts,
+            self.taker.to_account_info(),
+            self.taker.to_account_info(),
+            &self.token_program,
+            &self.associated_token_program,
+            &self.system_program,
+        )?;
+        empty_and_close_leg_vaults(
+            &self.escrow,
+            offer_accounts,
+            &self.taker.key(),
+            self.maker.to_account_info(),
+            &self.token_program,
+            signer_seeds,
+        )
+    }
+
+    // Hands the asset over along with the tokens: out of the escrow's custody to the taker, or
+    // from the taker to the maker who bid for it. `remaining_accounts` carries its accounts
+    pub fn take_asset(
+        &self,
+        asset: AssetLeg,
+        remaining_accounts: &'info [AccountInfo<'info>],
+    ) -> Result<()> {
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"escrow",
+            self.maker.to_account_info().key.as_ref(),
+            &self.escrow.seed.to_le_bytes(),
+            &[self.escrow.bump],
+        ]];
+        let (owner, new_owner, signer_seeds) = match asset.offered {
+            true => (
+                self.escrow.to_account_info(),
+                self.taker.to_account_info(),
+                signer_seeds,
+            ),
+            false => (
+                self.taker.to_account_info(),
+                self.maker.to_account_info(),
+                &[][..],
+            ),
         };
-        let cpi_close_program = self.token_program.to_account_info();
-        let cpi_close_ctx = CpiContext::new_with_signer(
-            cpi_close_program,
-            close_accounts,
-            signer_seeds
-        );
-        close_account(cpi_close_ctx)?;
+        transfer_asset(
+            &asset,
+            remaining_accounts,
+            &owner,
+            &new_owner,
+            &self.taker.to_account_info(),
+            &self.system_program.to_account_info(),
+            signer_seeds,
+        )
+    }
+
+    // Pays every requested leg to the maker, plus the marketplace fee on it to the treasury
+    pub fn deposit_legs(&self, remaining_accounts: &'info [AccountInfo<'info>]) -> Result<()> {
+        let token_program = self.token_program.key();
+        for (leg, accounts) in self
+            .escrow
+            .receive_legs
+            .iter()
+            .zip(remaining_accounts.chunks_exact(ACCOUNTS_PER_RECEIVE_LEG))
+        {
+            let [mint, taker_ata, maker_ata, treasury_ata] = accounts else {
+                unreachable!();
+            };
+            require_keys_eq!(mint.key(), leg.mint, EscrowError::InvalidBundleAccount);
+            let decimals = InterfaceAccount::<Mint>::try_from(mint)?.decimals;
+            check_bundle_ata(taker_ata, &leg.mint, &self.taker.key(), &token_program)?;
+            check_bundle_ata(maker_ata, &leg.mint, &self.maker.key(), &token_program)?;
+            check_bundle_ata(
+                treasury_ata,
+                &leg.mint,
+                &self.treasury.key(),
+                &token_program,
+            )?;
+
+            self.create_ata(maker_ata, self.maker.to_account_info(), mint)?;
+            let transfer_accounts = TransferChecked {
+                from: taker_ata.clone(),
+                mint: mint.clone(),
+                to: maker_ata.clone(),
+                authority: self.taker.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), transfer_accounts);
+            transfer_checked(cpi_ctx, leg.amount, decimals)?;
+
+            // The treasury only needs an account for this mint when there is a fee to collect
+            if self.config.fee_for(leg.amount) > 0 {
+                self.create_ata(treasury_ata, self.treasury.to_account_info(), mint)?;
+            }
+            let transfer_accounts = TransferChecked {
+                from: taker_ata.clone(),
+                mint: mint.clone(),
+                to: treasury_ata.clone(),
+                authority: self.taker.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), transfer_accounts);
+            self.config.pay_fee(cpi_ctx, leg.amount, decimals)?;
+        }
         Ok(())
     }
-}
\ No newline at end of file
+
+    fn create_ata(
+        &self,
+        ata: &AccountInfo<'info>,
+        authority: AccountInfo<'info>,
+        mint: &AccountInfo<'info>,
+    ) -> Result<()> {
+        let create_accounts = Create {
+            payer: self.taker.to_account_info(),
+            associated_token: ata.clone(),
+            authority,
+            mint: mint.clone(),
+            system_program: self.system_program.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+        };
+        create_idempotent(CpiContext::new(
+            self.associated_token_program.to_account_info(),
+            create_accounts,
+        ))
+    }
+
+    // Takes the escrow off its book page, if it is listed
+    pub fn delist(&mut self) {
+        if let Some(book_page) = &mut self.book_page {
+            book_page.remove(&self.escrow.key());
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::{errors::EscrowError, ArbitrationStatus, Config, Escrow};
+
+#[derive(Accounts)]
+pub struct TakeArbitrated<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+    pub maker: SystemAccount<'info>,
+    pub mint_b: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_ata_b: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        has_one = maker,
+        has_one = mint_b,
+        seeds = [b"escrow", maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.arbitration_status() == Some(ArbitrationStatus::Open) @ EscrowError::InvalidStatus,
+        constraint = !escrow.is_expired(Clock::get()?.unix_timestamp) @ EscrowError::Expired,
+        constraint = escrow.can_be_taken_by(&taker.key()) @ EscrowError::InvalidTaker,
+    )]
+    pub escrow: Account<'info, Escrow>,
+    // The taker's payment and fee wait here until delivery is confirmed or the arbiter rules
+    #[account(
+        init,
+        payer = taker,
+        associated_token::mint = mint_b,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_b: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> TakeArbitrated<'info> {
+    // The fee is held with the payment so the taker gets it back if the trade is unwound
+    pub fn deposit(&mut self) -> Result<()> {
+        let fee = self.config.fee_for(self.escrow.receive);
+        let amount = self
+            .escrow
+            .receive
+            .checked_add(fee)
+            .ok_or(EscrowError::Overflow)?;
+
+        let transfer_accounts = TransferChecked {
+            from: self.taker_ata_b.to_account_info(),
+            mint: self.mint_b.to_account_info(),
+            to: self.vault_b.to_account_info(),
+            authority: self.taker.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), transfer_accounts);
+        transfer_checked(cpi_ctx, amount, self.mint_b.decimals)
+    }
+
+    pub fn lock(&mut self) -> Result<()> {
+        let fee = self.config.fee_for(self.escrow.receive);
+        let taker = self.taker.key();
+        let arbitration = self
+            .escrow
+            .arbitration
+            .as_mut()
+            .ok_or(EscrowError::InvalidStatus)?;
+        arbitration.taker = taker;
+        arbitration.fee = fee;
+        arbitration.taken_at = Clock::get()?.unix_timestamp;
+        arbitration.status = ArbitrationStatus::Taken;
+        Ok(())
+    }
+}
@@ -0,0 +1,281 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::{
+        create_idempotent, get_associated_token_address_with_program_id, AssociatedToken, Create,
+    },
+    token_interface::{
+        close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface,
+        TransferChecked,
+    },
+};
+
+use crate::{create_treasury_ata, errors::EscrowError, BookPage, Config, Escrow};
+
+// Accounts passed per escrow in `remaining_accounts`: escrow, vault, maker, maker's token B
+// account, the offer book page the escrow is listed on or, for an unlisted escrow, the program
+// id in its place as for a missing optional account
+const ACCOUNTS_PER_ESCROW: usize = 5;
+
+#[derive(Accounts)]
+pub struct TakeMany<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    pub mint_a: InterfaceAccount<'info, Mint>,
+    pub mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = mint_a,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_ata_a: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_ata_b: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = treasury,
+    )]
+    pub config: Account<'info, Config>,
+    pub treasury: SystemAccount<'info>,
+    // Only created once there is a fee to collect, so fee-free takes skip its rent
+    #[account(mut)]
+    pub treasury_ata_b: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> TakeMany<'info> {
+    // Takes every escrow passed in full, failing the whole batch if any of them cannot be
+    // taken, changed since the revision the taker pinned for it, the total cost, fees
+    // included, goes over `max_spend` or the token A received falls short of `min_receive_a`
+    pub fn take_many(
+        &mut self,
+        max_spend: u64,
+        min_receive_a: u64,
+        revisions: Vec<u32>,
+        remaining_accounts: &'info [AccountInfo<'info>],
+    ) -> Result<()> {
+        require!(
+            !remaining_accounts.is_empty() && remaining_accounts.len() % ACCOUNTS_PER_ESCROW == 0,
+            EscrowError::InvalidEscrowAccount
+        );
+        require!(
+            revisions.len() == remaining_accounts.len() / ACCOUNTS_PER_ESCROW,
+            EscrowError::InvalidEscrowAccount
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let mut spent: u64 = 0;
+        let mut received: u64 = 0;
+        for (accounts, revision) in remaining_accounts
+            .chunks_exact(ACCOUNTS_PER_ESCROW)
+            .zip(revisions)
+        {
+            let [escrow, vault, maker, maker_ata_b, book_page] = accounts else {
+                unreachable!();
+            };
+            let escrow_account = Account::<Escrow>::try_from(escrow)?;
+
+            require_keys_eq!(
+                escrow_account.maker,
+                maker.key(),
+                EscrowError::InvalidEscrowAccount
+            );
+            require_keys_eq!(
+                escrow_account.mint_a,
+                self.mint_a.key(),
+                EscrowError::InvalidEscrowAccount
+            );
+            require_keys_eq!(
+                escrow_account.mint_b,
+                self.mint_b.key(),
+                EscrowError::InvalidEscrowAccount
+            );
+            require!(!escrow_account.is_expired(now), EscrowError::Expired);
+            require!(
+                escrow_account.can_be_taken_by(&self.taker.key()),
+                EscrowError::InvalidTaker
+            );
+            require!(
+                escrow_account.matches_revision(Some(revision)),
+                EscrowError::RevisionMismatch
+            );
+            require!(!escrow_account.is_bundle(), EscrowError::BundleUnsupported);
+            require!(!escrow_account.is_asset(), EscrowError::AssetUnsupported);
+            require!(
+                !escrow_account.is_arbitrated(),
+                EscrowError::ArbitrationUnsupported
+            );
+            require!(
+                !escrow_account.is_milestone(),
+                EscrowError::MilestoneUnsupported
+            );
+            require!(
+                !escrow_account.is_hash_locked(),
+                EscrowError::HashLockUnsupported
+            );
+
+            let receive = escrow_account.current_receive(now);
+            spent = spent
+                .checked_add(receive)
+                .and_then(|spent| spent.checked_add(self.config.fee_for(receive)))
+                .ok_or(EscrowError::Overflow)?;
+            require!(spent <= max_spend, EscrowError::MaxSpendExceeded);
+
+            self.deposit(maker, maker_ata_b, receive)?;
+            self.delist(&escrow_account, book_page)?;
+            let amount_a = self.withdraw_and_close(escrow_account, vault, maker)?;
+            received = received.checked_add(amount_a).ok_or(EscrowError::Overflow)?;
+        }
+        require!(received >= min_receive_a, EscrowError::MinReceiveNotMet);
+        Ok(())
+    }
+
+    pub fn deposit(
+        &self,
+        maker: &'info AccountInfo<'info>,
+        maker_ata_b: &'info AccountInfo<'info>,
+        amount: u64,
+    ) -> Result<()> {
+        require_keys_eq!(
+            maker_ata_b.key(),
+            get_associated_token_address_with_program_id(
+                maker.key,
+                &self.mint_b.key(),
+                &self.token_program.key(),
+            ),
+            EscrowError::InvalidEscrowAccount
+        );
+        let create_accounts = Create {
+            payer: self.taker.to_account_info(),
+            associated_token: maker_ata_b.to_account_info(),
+            authority: maker.to_account_info(),
+            mint: self.mint_b.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+        };
+        create_idempotent(CpiContext::new(
+            self.associated_token_program.to_account_info(),
+            create_accounts,
+        ))?;
+
+        let transfer_accounts = TransferChecked {
+            from: self.taker_ata_b.to_account_info(),
+            mint: self.mint_b.to_account_info(),
+            to: maker_ata_b.to_account_info(),
+            authority: self.taker.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), transfer_accounts);
+        transfer_checked(cpi_ctx, amount, self.mint_b.decimals)?;
+        self.pay_fee(amount)
+    }
+
+    pub fn pay_fee(&self, amount: u64) -> Result<()> {
+        if self.config.fee_for(amount) == 0 {
+            return Ok(());
+        }
+        create_treasury_ata(
+            &self.treasury_ata_b,
+            self.treasury.to_account_info(),
+            self.mint_b.to_account_info(),
+            self.taker.to_account_info(),
+            &self.token_program,
+            &self.associated_token_program,
+            &self.system_program,
+        )?;
+        let transfer_accounts = TransferChecked {
+            from: self.taker_ata_b.to_account_info(),
+            mint: self.mint_b.to_account_info(),
+            to: self.treasury_ata_b.to_account_info(),
+            authority: self.taker.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), transfer_accounts);
+        self.config.pay_fee(cpi_ctx, amount, self.mint_b.decimals)
+    }
+
+    // Escrows can sit on different pages, so each brings its own and it is written back here
+    pub fn delist(
+        &self,
+        escrow: &Account<'info, Escrow>,
+        book_page: &'info AccountInfo<'info>,
+    ) -> Result<()> {
+        if !escrow.is_listed() {
+            require_keys_eq!(book_page.key(), crate::ID, EscrowError::InvalidBookAccounts);
+            return Ok(());
+        }
+        let mut page = Account::<BookPage>::try_from(book_page)?;
+        require!(
+            page.mint_a == escrow.mint_a
+                && page.mint_b == escrow.mint_b
+                && page.page == escrow.book_page,
+            EscrowError::InvalidEscrowAccount
+        );
+        page.remove(&escrow.key());
+        page.exit(&crate::ID)
+    }
+
+    // Sends the whole vault to the taker and closes the escrow, returning the token A released
+    pub fn withdraw_and_close(
+        &mut self,
+        escrow: Account<'info, Escrow>,
+        vault: &'info AccountInfo<'info>,
+        maker: &'info AccountInfo<'info>,
+    ) -> Result<u64> {
+        let vault_account = InterfaceAccount::<TokenAccount>::try_from(vault)?;
+        require_keys_eq!(
+            vault_account.owner,
+            escrow.key(),
+            EscrowError::InvalidEscrowAccount
+        );
+        require_keys_eq!(
+            vault_account.mint,
+            self.mint_a.key(),
+            EscrowError::InvalidEscrowAccount
+        );
+
+        let seed = escrow.seed.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] =
+            &[&[b"escrow", maker.key.as_ref(), seed.as_ref(), &[escrow.bump]]];
+
+        let transfer_accounts = TransferChecked {
+            from: vault.to_account_info(),
+            mint: self.mint_a.to_account_info(),
+            to: self.taker_ata_a.to_account_info(),
+            authority: escrow.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            transfer_accounts,
+            signer_seeds,
+        );
+        transfer_checked(cpi_ctx, vault_account.amount, self.mint_a.decimals)?;
+
+        // Same split as `Take`: vault rent to the taker, escrow rent back to the maker
+        let close_accounts = CloseAccount {
+            account: vault.to_account_info(),
+            destination: self.taker.to_account_info(),
+            authority: escrow.to_account_info(),
+        };
+        let close_cpi_ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            close_accounts,
+            signer_seeds,
+        );
+        close_account(close_cpi_ctx)?;
+
+        escrow.close(maker.to_account_info())?;
+        Ok(vault_account.amount)
+    }
+}
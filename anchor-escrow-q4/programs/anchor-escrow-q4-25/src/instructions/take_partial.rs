@@ -0,0 +1,202 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::{
+    create_treasury_ata, empty_and_close_vault, errors::EscrowError, BookPage, Config, Escrow,
+};
+
+#[derive(Accounts)]
+#[instruction(amount_b: u64, revision: Option<u32>)]
+pub struct TakePartial<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+
+    pub mint_a: InterfaceAccount<'info, Mint>,
+    pub mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = mint_a,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_ata_a: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_ata_b: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = mint_b,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_ata_b: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = treasury,
+    )]
+    pub config: Account<'info, Config>,
+    pub treasury: SystemAccount<'info>,
+    // Only created once there is a fee to collect, so fee-free takes skip its rent
+    #[account(mut)]
+    pub treasury_ata_b: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        has_one = maker,
+        has_one = mint_a,
+        has_one = mint_b,
+        seeds = [b"escrow", maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+        constraint = !escrow.is_expired(Clock::get()?.unix_timestamp) @ EscrowError::Expired,
+        constraint = escrow.can_be_taken_by(&taker.key()) @ EscrowError::InvalidTaker,
+        constraint = escrow.matches_revision(revision) @ EscrowError::RevisionMismatch,
+        constraint = !escrow.is_auction() @ EscrowError::AuctionUnsupported,
+        constraint = !escrow.is_bundle() @ EscrowError::BundleUnsupported,
+        constraint = !escrow.is_asset() @ EscrowError::AssetUnsupported,
+        constraint = !escrow.is_arbitrated() @ EscrowError::ArbitrationUnsupported,
+        constraint = !escrow.is_milestone() @ EscrowError::MilestoneUnsupported,
+        constraint = !escrow.is_hash_locked() @ EscrowError::HashLockUnsupported,
+        constraint = !escrow.is_listed() || book_page.is_some() @ EscrowError::InvalidBookAccounts,
+    )]
+    pub escrow: Account<'info, Escrow>,
+    // Only for listed escrows, which must bring it so their entry stays current
+    #[account(
+        mut,
+        seeds = [
+            b"book_page",
+            escrow.mint_a.as_ref(),
+            escrow.mint_b.as_ref(),
+            escrow.book_page.to_le_bytes().as_ref(),
+        ],
+        bump = book_page.bump,
+    )]
+    pub book_page: Option<Account<'info, BookPage>>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> TakePartial<'info> {
+    pub fn deposit(&mut self, amount: u64) -> Result<()> {
+        let transfer_accounts = TransferChecked {
+            from: self.taker_ata_b.to_account_info(),
+            mint: self.mint_b.to_account_info(),
+            to: self.maker_ata_b.to_account_info(),
+            authority: self.taker.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), transfer_accounts);
+        transfer_checked(cpi_ctx, amount, self.mint_b.decimals)?;
+        self.pay_fee(amount)?;
+
+        self.escrow.receive -= amount;
+        Ok(())
+    }
+
+    pub fn pay_fee(&self, amount: u64) -> Result<()> {
+        if self.config.fee_for(amount) == 0 {
+            return Ok(());
+        }
+        create_treasury_ata(
+            &self.treasury_ata_b,
+            self.treasury.to_account_info(),
+            self.mint_b.to_account_info(),
+            self.taker.to_account_info(),
+            &self.token_program,
+            &self.associated_token_program,
+            &self.system_program,
+        )?;
+        let transfer_accounts = TransferChecked {
+            from: self.taker_ata_b.to_account_info(),
+            mint: self.mint_b.to_account_info(),
+            to: self.treasury_ata_b.to_account_info(),
+            authority: self.taker.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), transfer_accounts);
+        self.config.pay_fee(cpi_ctx, amount, self.mint_b.decimals)
+    }
+
+    pub fn withdraw(&mut self, amount: u64) -> Result<()> {
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"escrow",
+            self.maker.to_account_info().key.as_ref(),
+            &self.escrow.seed.to_le_bytes(),
+            &[self.escrow.bump],
+        ]];
+
+        let transfer_accounts = TransferChecked {
+            from: self.vault.to_account_info(),
+            mint: self.mint_a.to_account_info(),
+            to: self.taker_ata_a.to_account_info(),
+            authority: self.escrow.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            transfer_accounts,
+            signer_seeds,
+        );
+        transfer_checked(cpi_ctx, amount, self.mint_a.decimals)?;
+
+        self.escrow.offer -= amount;
+        Ok(())
+    }
+
+    // The last fill closes the vault and escrow, returning rent to the maker who paid it
+    pub fn close_if_filled(&mut self) -> Result<()> {
+        if self.escrow.receive > 0 {
+            return Ok(());
+        }
+
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"escrow",
+            self.maker.to_account_info().key.as_ref(),
+            &self.escrow.seed.to_le_bytes(),
+            &[self.escrow.bump],
+        ]];
+
+        // Anything donated to the vault goes to the last taker along with the fill
+        self.vault.reload()?;
+        empty_and_close_vault(
+            &self.vault,
+            &self.mint_a,
+            self.taker_ata_a.to_account_info(),
+            self.maker.to_account_info(),
+            self.escrow.to_account_info(),
+            self.token_program.to_account_info(),
+            signer_seeds,
+        )?;
+
+        self.escrow.close(self.maker.to_account_info())
+    }
+
+    // Reprices the escrow's book entry, if it is listed
+    pub fn sync_listing(&mut self) {
+        if let Some(book_page) = &mut self.book_page {
+            book_page.sync(self.escrow.key(), &self.escrow);
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+use solana_program::ed25519_program;
+use solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
+
+use crate::{
+    create_treasury_ata, empty_and_close_vault, errors::EscrowError, parse_ed25519_instruction,
+    BookPage, Config, Escrow, TakeIntent,
+};
+
+#[derive(Accounts)]
+#[instruction(intent: TakeIntent)]
+pub struct TakeWithIntent<'info> {
+    // Submits the transaction and pays its fee and any rent, on the taker's behalf
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+    // Authorizes the take through the signed intent instead of signing the transaction
+    pub taker: SystemAccount<'info>,
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+
+    pub mint_a: InterfaceAccount<'info, Mint>,
+    pub mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        associated_token::mint = mint_a,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_ata_a: InterfaceAccount<'info, TokenAccount>,
+    // Must have approved `delegate` for at least the price plus the fee
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_ata_b: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        associated_token::mint = mint_b,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_ata_b: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = treasury,
+    )]
+    pub config: Account<'info, Config>,
+    pub treasury: SystemAccount<'info>,
+    // Only created once there is a fee to collect, so fee-free takes skip its rent
+    #[account(mut)]
+    pub treasury_ata_b: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker,
+        has_one = mint_a,
+        has_one = mint_b,
+        address = intent.escrow @ EscrowError::InvalidIntent,
+        seeds = [b"escrow", maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+        constraint = !escrow.is_expired(Clock::get()?.unix_timestamp) @ EscrowError::Expired,
+        constraint = escrow.can_be_taken_by(&taker.key()) @ EscrowError::InvalidTaker,
+        constraint = !escrow.is_bundle() @ EscrowError::BundleUnsupported,
+        constraint = !escrow.is_asset() @ EscrowError::AssetUnsupported,
+        constraint = !escrow.is_arbitrated() @ EscrowError::ArbitrationUnsupported,
+        constraint = !escrow.is_milestone() @ EscrowError::MilestoneUnsupported,
+        constraint = !escrow.is_hash_locked() @ EscrowError::HashLockUnsupported,
+        constraint = !escrow.is_listed() || book_page.is_some() @ EscrowError::InvalidBookAccounts,
+    )]
+    pub escrow: Account<'info, Escrow>,
+    // Only for listed escrows, which must bring it so they come off the book
+    #[account(
+        mut,
+        seeds = [
+            b"book_page",
+            escrow.mint_a.as_ref(),
+            escrow.mint_b.as_ref(),
+            escrow.book_page.to_le_bytes().as_ref(),
+        ],
+        bump = book_page.bump,
+    )]
+    pub book_page: Option<Account<'info, BookPage>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    // Program-wide delegate takers approve once on their token B accounts
+    #[account(
+        seeds = [b"delegate"],
+        bump,
+    )]
+    pub delegate: SystemAccount<'info>,
+    #[account(
+        address = solana_program::sysvar::instructions::ID @ EscrowError::InvalidSignature
+    )]
+    /// CHECK: Instructions sysvar account
+    pub instruction_sysvar: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> TakeWithIntent<'info> {
+    // The instruction right before this one must be the ed25519 program checking the
+    // taker's signature over `intent`
+    pub fn verify_ed25519_signature(&self, intent: &TakeIntent) -> Result<()> {
+        let sysvar = self.instruction_sysvar.to_account_info();
+        let cur_index = load_current_index_checked(&sysvar)? as usize;
+        let ix = load_instruction_at_checked(
+            cur_index
+                .checked_sub(1)
+                .ok_or(EscrowError::InvalidSignature)?,
+            &sysvar,
+        )?;
+
+        require_keys_eq!(
+            ix.program_id,
+            ed25519_program::ID,
+            EscrowError::InvalidSignature
+        );
+        require_eq!(ix.accounts.len(), 0, EscrowError::InvalidSignature);
+
+        let (public_key, message) =
+            parse_ed25519_instruction(&ix.data).ok_or(EscrowError::InvalidSignature)?;
+        require!(
+            public_key == self.taker.key().as_ref(),
+            EscrowError::InvalidSignature
+        );
+        require!(
+            message == intent.try_to_vec()?.as_slice(),
+            EscrowError::InvalidIntent
+        );
+        Ok(())
+    }
+
+    // Price the take and check it against the limits the taker signed
+    pub fn check_intent(&self, intent: &TakeIntent) -> Result<u64> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(now < intent.expires_at, EscrowError::Expired);
+        require!(
+            intent.mint_a == self.escrow.mint_a
+                && intent.mint_b == self.escrow.mint_b
+                && intent.revision == self.escrow.revision,
+            EscrowError::InvalidIntent
+        );
+        require!(
+            self.escrow.offer >= intent.min_offer,
+            EscrowError::InvalidIntent
+        );
+
+        let receive = self.escrow.current_receive(now);
+        require!(receive <= intent.max_receive, EscrowError::InvalidIntent);
+        Ok(receive)
+    }
+
+    pub fn deposit(&mut self, amount: u64, bumps: &TakeWithIntentBumps) -> Result<()> {
+        let signer_seeds: &[&[&[u8]]] = &[&[b"delegate", &[bumps.delegate]]];
+
+        let transfer_accounts = TransferChecked {
+            from: self.taker_ata_b.to_account_info(),
+            mint: self.mint_b.to_account_info(),
+            to: self.maker_ata_b.to_account_info(),
+            authority: self.delegate.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            transfer_accounts,
+            signer_seeds,
+        );
+        transfer_checked(cpi_ctx, amount, self.mint_b.decimals)?;
+
+        if self.config.fee_for(amount) == 0 {
+            return Ok(());
+        }
+        create_treasury_ata(
+            &self.treasury_ata_b,
+            self.treasury.to_account_info(),
+            self.mint_b.to_account_info(),
+            self.relayer.to_account_info(),
+            &self.token_program,
+            &self.associated_token_program,
+            &self.system_program,
+        )?;
+        let transfer_accounts = TransferChecked {
+            from: self.taker_ata_b.to_account_info(),
+            mint: self.mint_b.to_account_info(),
+            to: self.treasury_ata_b.to_account_info(),
+            authority: self.delegate.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            transfer_accounts,
+            signer_seeds,
+        );
+        self.config.pay_fee(cpi_ctx, amount, self.mint_b.decimals)
+    }
+
+    // Same as `Take`, except the vault's rent goes to the relayer who fronted the costs
+    pub fn withdraw_and_close_vault(&mut self) -> Result<()> {
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"escrow",
+            self.maker.to_account_info().key.as_ref(),
+            &self.escrow.seed.to_le_bytes(),
+            &[self.escrow.bump],
+        ]];
+
+        empty_and_close_vault(
+            &self.vault,
+            &self.mint_a,
+            self.taker_ata_a.to_account_info(),
+            self.relayer.to_account_info(),
+            self.escrow.to_account_info(),
+            self.token_program.to_account_info(),
+            signer_seeds,
+        )
+    }
+
+    // Takes the escrow off its book page, if it is listed
+    pub fn delist(&mut self) {
+        if let Some(book_page) = &mut self.book_page {
+            book_page.remove(&self.escrow.key());
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use anchor_lang::prelude::*;
+
+use crate::{errors::EscrowError, BookPage, Escrow, OfferBook};
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+#[derive(Accounts)]
+pub struct UpdateOffer<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    pub mint_a: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_ata_a: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        has_one = mint_a,
+        has_one = maker,
+        constraint = !escrow.is_auction() @ EscrowError::AuctionUnsupported,
+        constraint = !escrow.is_bundle() @ EscrowError::BundleUnsupported,
+        constraint = !escrow.is_hash_locked() @ EscrowError::HashLockUnsupported,
+        constraint = !escrow.is_arbitrated() @ EscrowError::ArbitrationUnsupported,
+        constraint = !escrow.is_milestone() @ EscrowError::MilestoneUnsupported,
+        seeds = [b"escrow", maker.key().as_ref(), &escrow.seed.to_le_bytes()],
+        bump = escrow.bump,
+        constraint = !escrow.is_listed() || offer_book.is_some() @ EscrowError::InvalidBookAccounts,
+        constraint = !escrow.is_listed() || book_page.is_some() @ EscrowError::InvalidBookAccounts,
+    )]
+    pub escrow: Account<'info, Escrow>,
+    // Both only for listed escrows, whose entry is repriced and must still clear the minimum
+    #[account(
+        seeds = [b"book", escrow.mint_a.as_ref(), escrow.mint_b.as_ref()],
+        bump = offer_book.bump,
+    )]
+    pub offer_book: Option<Account<'info, OfferBook>>,
+    #[account(
+        mut,
+        seeds = [
+            b"book_page",
+            escrow.mint_a.as_ref(),
+            escrow.mint_b.as_ref(),
+            escrow.book_page.to_le_bytes().as_ref(),
+        ],
+        bump = book_page.bump,
+    )]
+    pub book_page: Option<Account<'info, BookPage>>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> UpdateOffer<'info> {
+    // Moves the vault to the new `offer` amount, topping it up or sending the difference back
+    pub fn update_offer(&mut self, offer: u64, receive: u64) -> Result<()> {
+        require!(offer > 0 && receive > 0, EscrowError::InvalidAmount);
+        if let Some(offer_book) = &self.offer_book {
+            require!(offer >= offer_book.min_offer, EscrowError::OfferTooSmall);
+        }
+
+        if offer > self.escrow.offer {
+            self.deposit(offer - self.escrow.offer)?;
+        } else if offer < self.escrow.offer {
+            self.withdraw(self.escrow.offer - offer)?;
+        }
+
+        self.escrow.offer = offer;
+        self.escrow.receive = receive;
+        self.escrow.revision = self
+            .escrow
+            .revision
+            .checked_add(1)
+            .ok_or(EscrowError::Overflow)?;
+        Ok(())
+    }
+
+    pub fn deposit(&mut self, amount: u64) -> Result<()> {
+        let transfer_accounts = TransferChecked {
+            from: self.maker_ata_a.to_account_info(),
+            mint: self.mint_a.to_account_info(),
+            to: self.vault.to_account_info(),
+            authority: self.maker.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), transfer_accounts);
+
+        transfer_checked(cpi_ctx, amount, self.mint_a.decimals)
+    }
+
+    pub fn withdraw(&mut self, amount: u64) -> Result<()> {
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"escrow",
+            self.maker.to_account_info().key.as_ref(),
+            &self.escrow.seed.to_le_bytes(),
+            &[self.escrow.bump],
+        ]];
+
+        let transfer_accounts = TransferChecked {
+            from: self.vault.to_account_info(),
+            mint: self.mint_a.to_account_info(),
+            to: self.maker_ata_a.to_account_info(),
+            authority: self.escrow.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            transfer_accounts,
+            signer_seeds,
+        );
+
+        transfer_checked(cpi_ctx, amount, self.mint_a.decimals)
+    }
+
+    // Reprices the escrow's book entry, if it is listed
+    pub fn sync_listing(&mut self) {
+        if let Some(book_page) = &mut self.book_page {
+            book_page.sync(self.escrow.key(), &self.escrow);
+        }
+    }
+}
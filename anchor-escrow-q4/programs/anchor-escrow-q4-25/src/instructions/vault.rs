@@ -0,0 +1,194 @@
+use anchor_lang::{prelude::*, system_program};
+use anchor_spl::{
+    associated_token::{create_idempotent, AssociatedToken, Create},
+    token::spl_token::native_mint,
+    token_interface::{
+        close_account, sync_native, transfer_checked, CloseAccount, Mint, SyncNative, TokenAccount,
+        TokenInterface, TransferChecked,
+    },
+};
+
+use crate::{check_bundle_ata, errors::EscrowError, Escrow};
+
+// Accounts passed per offered bundle leg in `remaining_accounts` when its vault is emptied:
+// mint, vault, recipient's token account
+pub const ACCOUNTS_PER_OFFER_LEG: usize = 3;
+
+// Sends everything in `vault` to `to` and closes it, returning its rent to `rent_to`.
+// The whole balance goes out rather than the recorded amount, so a donation to the vault
+// can never keep it from closing
+pub fn empty_and_close_vault<'info>(
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    to: AccountInfo<'info>,
+    rent_to: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let transfer_accounts = TransferChecked {
+        from: vault.to_account_info(),
+        mint: mint.to_account_info(),
+        to,
+        authority: authority.clone(),
+    };
+    let transfer_cpi_ctx =
+        CpiContext::new_with_signer(token_program.clone(), transfer_accounts, signer_seeds);
+    transfer_checked(transfer_cpi_ctx, vault.amount, mint.decimals)?;
+
+    let close_accounts = CloseAccount {
+        account: vault.to_account_info(),
+        destination: rent_to,
+        authority,
+    };
+    let close_cpi_ctx = CpiContext::new_with_signer(token_program, close_accounts, signer_seeds);
+    close_account(close_cpi_ctx)
+}
+
+// A side traded in native SOL keeps a wrapped SOL vault, but lets wallets that leave out their
+// token account for it pay or be paid in plain lamports
+pub fn is_native(mint: &Pubkey) -> bool {
+    *mint == native_mint::ID
+}
+
+// Moves `lamports` from `from` into the wrapped SOL `vault` and syncs its token balance
+pub fn wrap_into_vault<'info>(
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    from: AccountInfo<'info>,
+    lamports: u64,
+    token_program: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+) -> Result<()> {
+    let transfer_accounts = system_program::Transfer {
+        from,
+        to: vault.to_account_info(),
+    };
+    system_program::transfer(CpiContext::new(system_program, transfer_accounts), lamports)?;
+    let sync_accounts = SyncNative {
+        account: vault.to_account_info(),
+    };
+    sync_native(CpiContext::new(token_program, sync_accounts))
+}
+
+// Native SOL counterpart of `empty_and_close_vault`. The wrapped SOL vault is closed into
+// `escrow`, which then pays its balance to `to` and its rent to `rent_to` as plain lamports
+pub fn unwrap_and_close_vault<'info>(
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    escrow: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    rent_to: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let lamports = vault.get_lamports();
+    let close_accounts = CloseAccount {
+        account: vault.to_account_info(),
+        destination: escrow.clone(),
+        authority: escrow.clone(),
+    };
+    let close_cpi_ctx = CpiContext::new_with_signer(token_program, close_accounts, signer_seeds);
+    close_account(close_cpi_ctx)?;
+
+    // Lamports sent to the vault without a sync count as rent, so they can't get stuck either
+    let rent = lamports - vault.amount;
+    escrow.sub_lamports(lamports)?;
+    to.add_lamports(vault.amount)?;
+    rent_to.add_lamports(rent)?;
+    Ok(())
+}
+
+// Creates `owner`'s token account for every offered leg's mint that lacks one, at `payer`'s
+// expense, so the legs can be paid out to it
+pub fn create_leg_atas<'info>(
+    remaining_accounts: &'info [AccountInfo<'info>],
+    owner: AccountInfo<'info>,
+    payer: AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+    associated_token_program: &Program<'info, AssociatedToken>,
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    for accounts in remaining_accounts.chunks_exact(ACCOUNTS_PER_OFFER_LEG) {
+        let [mint, _vault, ata] = accounts else {
+            unreachable!();
+        };
+        let create_accounts = Create {
+            payer: payer.clone(),
+            associated_token: ata.clone(),
+            authority: owner.clone(),
+            mint: mint.clone(),
+            system_program: system_program.to_account_info(),
+            token_program: token_program.to_account_info(),
+        };
+        create_idempotent(CpiContext::new(
+            associated_token_program.to_account_info(),
+            create_accounts,
+        ))?;
+    }
+    Ok(())
+}
+
+// Creates `treasury`'s token account for `mint` at `payer`'s expense if it is missing. Takes
+// only call this once they owe a fee, so fee-free ones never pay its rent
+pub fn create_treasury_ata<'info>(
+    treasury_ata: &UncheckedAccount<'info>,
+    treasury: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    payer: AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+    associated_token_program: &Program<'info, AssociatedToken>,
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    check_bundle_ata(treasury_ata, mint.key, treasury.key, &token_program.key())?;
+    let create_accounts = Create {
+        payer,
+        associated_token: treasury_ata.to_account_info(),
+        authority: treasury,
+        mint,
+        system_program: system_program.to_account_info(),
+        token_program: token_program.to_account_info(),
+    };
+    create_idempotent(CpiContext::new(
+        associated_token_program.to_account_info(),
+        create_accounts,
+    ))
+}
+
+// Empties and closes the vault of every leg `escrow` offers on top of token A, paying the
+// tokens to `recipient`'s token accounts and the rent to the maker, who funded the vaults
+pub fn empty_and_close_leg_vaults<'info>(
+    escrow: &Account<'info, Escrow>,
+    remaining_accounts: &'info [AccountInfo<'info>],
+    recipient: &Pubkey,
+    maker: AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    require!(
+        remaining_accounts.len() == escrow.offer_legs.len() * ACCOUNTS_PER_OFFER_LEG,
+        EscrowError::InvalidBundleAccount
+    );
+
+    for (leg, accounts) in escrow
+        .offer_legs
+        .iter()
+        .zip(remaining_accounts.chunks_exact(ACCOUNTS_PER_OFFER_LEG))
+    {
+        let [mint, vault, to] = accounts else {
+            unreachable!();
+        };
+        require_keys_eq!(mint.key(), leg.mint, EscrowError::InvalidBundleAccount);
+        check_bundle_ata(vault, &leg.mint, &escrow.key(), &token_program.key())?;
+        check_bundle_ata(to, &leg.mint, recipient, &token_program.key())?;
+
+        empty_and_close_vault(
+            &InterfaceAccount::<TokenAccount>::try_from(vault)?,
+            &InterfaceAccount::<Mint>::try_from(mint)?,
+            to.clone(),
+            maker.clone(),
+            escrow.to_account_info(),
+            token_program.to_account_info(),
+            signer_seeds,
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use crate::{empty_and_close_vault, CounterOffer};
+
+#[derive(Accounts)]
+pub struct WithdrawCounter<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+    pub mint_b: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_ata_b: InterfaceAccount<'info, TokenAccount>,
+    // Works even after the escrow itself was taken or refunded
+    #[account(
+        mut,
+        close = taker,
+        has_one = taker,
+        seeds = [b"counter", counter.escrow.as_ref(), taker.key().as_ref()],
+        bump = counter.bump,
+    )]
+    pub counter: Account<'info, CounterOffer>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = counter,
+        associated_token::token_program = token_program,
+    )]
+    pub counter_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> WithdrawCounter<'info> {
+    pub fn withdraw_and_close_vault(&mut self) -> Result<()> {
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"counter",
+            self.counter.escrow.as_ref(),
+            self.taker.to_account_info().key.as_ref(),
+            &[self.counter.bump],
+        ]];
+
+        empty_and_close_vault(
+            &self.counter_vault,
+            &self.mint_b,
+            self.taker_ata_b.to_account_info(),
+            self.taker.to_account_info(),
+            self.counter.to_account_info(),
+            self.token_program.to_account_info(),
+            signer_seeds,
+        )
+    }
+}
@@ -1,5 +1,6 @@
 use anchor_lang::prelude::*;
 
+pub mod errors;
 pub mod instructions;
 pub mod state;
 
@@ -11,17 +12,165 @@ declare_id!("GYF9RjJd7d64HXWw79xvZT89XEpPuMiJUt1FQLNVWfCh");
 #[program]
 pub mod anchor_escrow_q4_25 {
     use super::*;
-    pub fn make(ctx: Context<Make>, seed: u64, offer_from_a: u64, receive: u64) -> Result<()> {
+    pub fn make<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Make<'info>>,
+        seed: u64,
+        offer_from_a: u64,
+        receive: u64,
+        expires_at: i64,
+        params: MakeParams,
+    ) -> Result<()> {
         ctx.accounts.deposit(offer_from_a)?;
-        ctx.accounts.init_escrow(seed, receive, &ctx.bumps)
+        ctx.accounts.init_escrow(
+            seed,
+            offer_from_a,
+            receive,
+            expires_at,
+            params.taker,
+            &ctx.bumps,
+        )?;
+        if let Some(auction) = params.auction {
+            ctx.accounts.set_auction(auction)?;
+        }
+        if let Some(hash) = params.hash_lock {
+            ctx.accounts.set_hash_lock(hash)?;
+        }
+        match params.asset {
+            Some(asset) => ctx
+                .accounts
+                .set_asset(asset, &params, ctx.remaining_accounts)?,
+            None => ctx.accounts.set_legs(
+                params.offer_legs,
+                params.receive_legs,
+                ctx.remaining_accounts,
+            )?,
+        }
+        if let Some(terms) = params.arbitration {
+            ctx.accounts.set_arbitration(terms)?;
+        }
+        ctx.accounts
+            .set_milestones(params.milestones, params.approver)?;
+        ctx.accounts.list_offer(params.page, &ctx.bumps)
     }
 
-    pub fn refund(ctx: Context<Refund>) -> Result<()> {
-        ctx.accounts.refund_and_close_vault()
+    pub fn refund<'info>(ctx: Context<'_, '_, 'info, 'info, Refund<'info>>) -> Result<()> {
+        ctx.accounts
+            .refund_and_close_vault(ctx.remaining_accounts)?;
+        ctx.accounts.delist();
+        Ok(())
     }
 
-    pub fn take(ctx: Context<Take>) -> Result<()> {
-        ctx.accounts.deposit(ctx.accounts.escrow.receive)?;
+    pub fn expire<'info>(ctx: Context<'_, '_, 'info, 'info, Expire<'info>>) -> Result<()> {
+        ctx.accounts.refund_and_close(ctx.remaining_accounts)?;
+        ctx.accounts.delist();
+        Ok(())
+    }
+
+    pub fn set_config(
+        ctx: Context<SetConfig>,
+        authority: Pubkey,
+        treasury: Pubkey,
+        fee_bps: u16,
+    ) -> Result<()> {
+        ctx.accounts
+            .set_config(authority, treasury, fee_bps, &ctx.bumps)
+    }
+
+    pub fn set_book_min_offer(ctx: Context<SetBookMinOffer>, min_offer: u64) -> Result<()> {
+        ctx.accounts.set_min_offer(min_offer, &ctx.bumps)
+    }
+
+    pub fn take<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Take<'info>>,
+        _revision: Option<u32>,
+        _preimage: Option<Vec<u8>>,
+    ) -> Result<()> {
+        let receive = ctx
+            .accounts
+            .escrow
+            .current_receive(Clock::get()?.unix_timestamp);
+        ctx.accounts.deposit(receive)?;
+        ctx.accounts.withdraw_and_close_vault()?;
+        match ctx.accounts.escrow.asset {
+            Some(asset) => ctx.accounts.take_asset(asset, ctx.remaining_accounts)?,
+            None => ctx.accounts.take_legs(ctx.remaining_accounts)?,
+        }
+        ctx.accounts.delist();
+        Ok(())
+    }
+
+    pub fn take_partial(
+        ctx: Context<TakePartial>,
+        amount_b: u64,
+        _revision: Option<u32>,
+    ) -> Result<()> {
+        let amount_a = ctx.accounts.escrow.offer_for(amount_b)?;
+        ctx.accounts.deposit(amount_b)?;
+        ctx.accounts.withdraw(amount_a)?;
+        ctx.accounts.sync_listing();
+        ctx.accounts.close_if_filled()
+    }
+
+    pub fn update_offer(ctx: Context<UpdateOffer>, offer: u64, receive: u64) -> Result<()> {
+        ctx.accounts.update_offer(offer, receive)?;
+        ctx.accounts.sync_listing();
+        Ok(())
+    }
+
+    pub fn take_arbitrated(ctx: Context<TakeArbitrated>) -> Result<()> {
+        ctx.accounts.deposit()?;
+        ctx.accounts.lock()
+    }
+
+    pub fn dispute_arbitrated(ctx: Context<DisputeArbitrated>) -> Result<()> {
+        ctx.accounts.dispute()
+    }
+
+    pub fn confirm_arbitrated(ctx: Context<SettleArbitrated>) -> Result<()> {
+        ctx.accounts.confirm()
+    }
+
+    pub fn resolve_arbitrated(ctx: Context<SettleArbitrated>, complete_trade: bool) -> Result<()> {
+        ctx.accounts.resolve(complete_trade)
+    }
+
+    pub fn release_milestone(ctx: Context<ReleaseMilestone>) -> Result<()> {
+        ctx.accounts.release()?;
+        ctx.accounts.close_if_done()
+    }
+
+    pub fn take_with_intent(ctx: Context<TakeWithIntent>, intent: TakeIntent) -> Result<()> {
+        ctx.accounts.verify_ed25519_signature(&intent)?;
+        let receive = ctx.accounts.check_intent(&intent)?;
+        ctx.accounts.deposit(receive, &ctx.bumps)?;
+        ctx.accounts.withdraw_and_close_vault()?;
+        ctx.accounts.delist();
+        Ok(())
+    }
+
+    pub fn make_counter(ctx: Context<MakeCounter>, receive: u64) -> Result<()> {
+        ctx.accounts.init_counter(receive, &ctx.bumps)?;
+        ctx.accounts.deposit()
+    }
+
+    pub fn accept_counter(ctx: Context<AcceptCounter>) -> Result<()> {
+        ctx.accounts.deposit()?;
+        ctx.accounts.withdraw_and_close_vault()?;
+        ctx.accounts.delist();
+        Ok(())
+    }
+
+    pub fn withdraw_counter(ctx: Context<WithdrawCounter>) -> Result<()> {
         ctx.accounts.withdraw_and_close_vault()
     }
+
+    pub fn take_many<'info>(
+        ctx: Context<'_, '_, 'info, 'info, TakeMany<'info>>,
+        max_spend: u64,
+        min_receive_a: u64,
+        revisions: Vec<u32>,
+    ) -> Result<()> {
+        ctx.accounts
+            .take_many(max_spend, min_receive_a, revisions, ctx.remaining_accounts)
+    }
 }
@@ -0,0 +1,52 @@
+use anchor_lang::prelude::*;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum ArbitrationStatus {
+    Open,     // Waiting for a taker, the maker can still refund
+    Taken,    // Both sides are in the vaults, waiting for the maker to confirm delivery
+    Disputed, // Only the arbiter can settle now, or either side once the ruling is overdue
+}
+
+// Set by the maker in `make`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct ArbitrationTerms {
+    pub arbiter: Pubkey,
+    pub dispute_window: i64,
+}
+
+// Holds a taken escrow's payment next to the offer until the maker confirms delivery or the
+// arbiter settles a dispute
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct Arbitration {
+    pub arbiter: Pubkey,
+    pub dispute_window: i64, // Seconds to raise a dispute after the take, and for the arbiter to rule after one
+    pub status: ArbitrationStatus,
+    pub taker: Pubkey, // Default until taken
+    pub fee: u64,      // Marketplace fee held in vault_b until the trade completes
+    pub taken_at: i64,
+    pub disputed_at: i64,
+}
+
+impl Arbitration {
+    pub fn new(terms: ArbitrationTerms) -> Self {
+        Self {
+            arbiter: terms.arbiter,
+            dispute_window: terms.dispute_window,
+            status: ArbitrationStatus::Open,
+            taker: Pubkey::default(),
+            fee: 0,
+            taken_at: 0,
+            disputed_at: 0,
+        }
+    }
+
+    pub fn dispute_window_open(&self, now: i64) -> bool {
+        now <= self.taken_at.saturating_add(self.dispute_window)
+    }
+
+    // An arbiter who hasn't ruled a window after the dispute lets either side unwind the trade,
+    // so the deposits can't be stranded by an arbiter who walked away
+    pub fn ruling_overdue(&self, now: i64) -> bool {
+        now > self.disputed_at.saturating_add(self.dispute_window)
+    }
+}
@@ -0,0 +1,71 @@
+use anchor_lang::prelude::*;
+use mpl_core::{
+    accounts::BaseAssetV1, instructions::TransferV1CpiBuilder, types::UpdateAuthority,
+    ID as CORE_PROGRAM_ID,
+};
+
+use crate::errors::EscrowError;
+
+// An mpl-core asset traded on top of an escrow's token sides
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct AssetLeg {
+    pub address: Pubkey,
+    pub offered: bool, // True when the maker sells the asset, false when the maker bids for it
+}
+
+// Checks that `asset` is a core asset held by `owner` and that `collection` is the one it
+// belongs to, which core requires to be passed along on transfer
+pub fn verify_asset(
+    asset: &AccountInfo,
+    collection: Option<&AccountInfo>,
+    owner: &Pubkey,
+) -> Result<()> {
+    require_keys_eq!(*asset.owner, CORE_PROGRAM_ID, EscrowError::InvalidAsset);
+    let base = BaseAssetV1::try_from(asset).map_err(|_| EscrowError::InvalidAsset)?;
+    require_keys_eq!(base.owner, *owner, EscrowError::NotAssetOwner);
+
+    let expected = match base.update_authority {
+        UpdateAuthority::Collection(collection) => Some(collection),
+        _ => None,
+    };
+    require!(
+        collection.map(|collection| collection.key()) == expected,
+        EscrowError::InvalidCollection
+    );
+    Ok(())
+}
+
+// Moves `leg`'s asset from `owner` to `new_owner`. `remaining_accounts` carries the asset, the
+// core program and, for an asset in a collection, the collection
+pub fn transfer_asset<'info>(
+    leg: &AssetLeg,
+    remaining_accounts: &'info [AccountInfo<'info>],
+    owner: &AccountInfo<'info>,
+    new_owner: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let (asset, core_program, collection) = match remaining_accounts {
+        [asset, core_program] => (asset, core_program, None),
+        [asset, core_program, collection] => (asset, core_program, Some(collection)),
+        _ => return err!(EscrowError::InvalidAsset),
+    };
+    require_keys_eq!(asset.key(), leg.address, EscrowError::InvalidAsset);
+    require_keys_eq!(
+        core_program.key(),
+        CORE_PROGRAM_ID,
+        EscrowError::InvalidAsset
+    );
+    verify_asset(asset, collection, owner.key)?;
+
+    TransferV1CpiBuilder::new(core_program)
+        .asset(asset)
+        .collection(collection)
+        .payer(payer)
+        .authority(Some(owner))
+        .new_owner(new_owner)
+        .system_program(Some(system_program))
+        .invoke_signed(signer_seeds)?;
+    Ok(())
+}
@@ -0,0 +1,24 @@
+use anchor_lang::prelude::*;
+
+// Declining price for an escrow: the amount of token B asked falls linearly from the
+// escrow's `receive` at `start_at` to `end_receive` once `duration` seconds have passed
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct DutchAuction {
+    pub end_receive: u64,
+    pub start_at: i64,
+    pub duration: i64,
+}
+
+impl DutchAuction {
+    pub fn is_valid(&self, start_receive: u64) -> bool {
+        self.duration > 0 && self.end_receive > 0 && self.end_receive <= start_receive
+    }
+
+    // Price at `now`, rounded up so the maker never gets less than the straight line
+    pub fn price_at(&self, start_receive: u64, now: i64) -> u64 {
+        let elapsed = now.saturating_sub(self.start_at).clamp(0, self.duration);
+        let decay =
+            (start_receive - self.end_receive) as u128 * elapsed as u128 / self.duration as u128;
+        start_receive - decay as u64
+    }
+}
@@ -0,0 +1,40 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+
+use crate::errors::EscrowError;
+
+// Most mints a bundle can hold on either side, bounded by what fits in one transaction
+pub const MAX_BUNDLE_MINTS: usize = 5;
+
+// A mint traded on top of an escrow's `mint_a` or `mint_b` side
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct BundleLeg {
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+// Legs added to the side whose first mint is `primary`: no more than the max with it,
+// and no mint twice
+pub fn validate_legs(primary: &Pubkey, legs: &[BundleLeg]) -> Result<()> {
+    require!(legs.len() < MAX_BUNDLE_MINTS, EscrowError::InvalidBundle);
+    for (i, leg) in legs.iter().enumerate() {
+        require!(leg.amount > 0, EscrowError::InvalidAmount);
+        require!(
+            leg.mint != *primary && legs[..i].iter().all(|other| other.mint != leg.mint),
+            EscrowError::InvalidBundle
+        );
+    }
+    Ok(())
+}
+
+// Checks that `account` is the `authority`'s associated token account for `mint`
+pub fn check_bundle_ata(
+    account: &AccountInfo,
+    mint: &Pubkey,
+    authority: &Pubkey,
+    token_program: &Pubkey,
+) -> Result<()> {
+    let expected = get_associated_token_address_with_program_id(authority, mint, token_program);
+    require_keys_eq!(account.key(), expected, EscrowError::InvalidBundleAccount);
+    Ok(())
+}
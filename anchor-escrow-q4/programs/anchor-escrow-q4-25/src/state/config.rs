@@ -0,0 +1,47 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Transfer};
+use anchor_spl::token_interface::{transfer_checked, TransferChecked};
+
+#[account]
+#[derive(InitSpace)]
+pub struct Config {
+    pub authority: Pubkey, // Can change the fee and treasury
+    pub treasury: Pubkey,  // Wallet whose mint B token accounts collect the fee
+    pub fee_bps: u16,      // Fee charged to takers on top of `receive`, in basis points
+    pub bump: u8,
+}
+
+impl Config {
+    // Rounded up, so splitting a fill into small takes cannot bring the fee down to zero
+    pub fn fee_for(&self, amount: u64) -> u64 {
+        (amount as u128 * self.fee_bps as u128).div_ceil(10_000) as u64
+    }
+
+    // Marketplace fee on a take of `amount`, paid by the taker on top of the price.
+    // `cpi_ctx` moves tokens from the taker's account to the treasury's
+    pub fn pay_fee<'info>(
+        &self,
+        cpi_ctx: CpiContext<'_, '_, '_, 'info, TransferChecked<'info>>,
+        amount: u64,
+        decimals: u8,
+    ) -> Result<()> {
+        let fee = self.fee_for(amount);
+        if fee == 0 {
+            return Ok(());
+        }
+        transfer_checked(cpi_ctx, fee, decimals)
+    }
+
+    // Same as `pay_fee` for takes priced in SOL, paid straight to the treasury wallet
+    pub fn pay_fee_in_sol<'info>(
+        &self,
+        cpi_ctx: CpiContext<'_, '_, '_, 'info, Transfer<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        let fee = self.fee_for(amount);
+        if fee == 0 {
+            return Ok(());
+        }
+        system_program::transfer(cpi_ctx, fee)
+    }
+}
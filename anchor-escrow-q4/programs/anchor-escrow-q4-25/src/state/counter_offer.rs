@@ -0,0 +1,15 @@
+use anchor_lang::prelude::*;
+
+#[account]
+#[derive(InitSpace)]
+pub struct CounterOffer {
+    pub escrow: Pubkey,
+    pub taker: Pubkey,
+    pub mint_a: Pubkey, // With `seed`, tells this escrow apart from one made again at its address
+    pub seed: u64,
+    pub offer: u64,    // Token A in the escrow when the counter was posted
+    pub revision: u32, // Escrow revision the counter was posted against
+    pub receive: u64,  // Token B proposed to the maker instead of the escrow's `receive`
+    pub fee: u64,      // Marketplace fee deposited on top, paid out only if accepted
+    pub bump: u8,
+}
@@ -0,0 +1,43 @@
+use anchor_lang::prelude::*;
+
+// Terms a taker signs off-chain so a relayer can submit the take for them. The borsh
+// encoding of this struct is the exact message covered by the ed25519 signature.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct TakeIntent {
+    pub escrow: Pubkey,
+    pub mint_a: Pubkey,   // Escrow's token A, should it be closed and made again
+    pub mint_b: Pubkey,   // Escrow's token B, likewise
+    pub revision: u32,    // Escrow revision whose terms the taker saw
+    pub min_offer: u64,   // Least token A the taker accepts
+    pub max_receive: u64, // Most token B the taker pays, before the marketplace fee
+    pub expires_at: i64,  // Unix timestamp after which the intent can no longer be used
+}
+
+// Signature offsets header of the ed25519 program: count and padding, then one 14 byte
+// entry per signature
+const SIGNATURE_OFFSETS_START: usize = 2;
+const SIGNATURE_OFFSETS_LEN: usize = 14;
+
+// Public key and message of the only signature checked by an ed25519 program instruction.
+// Both must sit in that instruction's own data, which is what web3.js produces.
+pub fn parse_ed25519_instruction(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    if data.len() < SIGNATURE_OFFSETS_START + SIGNATURE_OFFSETS_LEN || data[0] != 1 {
+        return None;
+    }
+    let offset = |field: usize| {
+        let at = SIGNATURE_OFFSETS_START + field * 2;
+        u16::from_le_bytes([data[at], data[at + 1]]) as usize
+    };
+    let [_, signature_ix, public_key_at, public_key_ix, message_at, message_len, message_ix] =
+        [0, 1, 2, 3, 4, 5, 6].map(offset);
+    if [signature_ix, public_key_ix, message_ix]
+        .iter()
+        .any(|ix| *ix != u16::MAX as usize)
+    {
+        return None;
+    }
+
+    let public_key = data.get(public_key_at..public_key_at + 32)?;
+    let message = data.get(message_at..message_at + message_len)?;
+    Some((public_key, message))
+}
@@ -0,0 +1,24 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::EscrowError;
+
+// Most tranches a milestone escrow can be split into
+pub const MAX_MILESTONES: usize = 10;
+
+// Tranches splitting the whole `offer`, each paid out when signed off
+pub fn validate_milestones(offer: u64, milestones: &[u64]) -> Result<()> {
+    require!(
+        milestones.len() <= MAX_MILESTONES,
+        EscrowError::InvalidMilestones
+    );
+    require!(
+        milestones.iter().all(|amount| *amount > 0),
+        EscrowError::InvalidAmount
+    );
+    let total = milestones
+        .iter()
+        .try_fold(0u64, |total, amount| total.checked_add(*amount))
+        .ok_or(EscrowError::Overflow)?;
+    require!(total == offer, EscrowError::InvalidMilestones);
+    Ok(())
+}
@@ -1,4 +1,34 @@
 use anchor_lang::prelude::*;
+use solana_program::hash::hash;
+
+use crate::errors::EscrowError;
+
+pub mod arbitration;
+pub use arbitration::*;
+
+pub mod asset;
+pub use asset::*;
+
+pub mod auction;
+pub use auction::*;
+
+pub mod bundle;
+pub use bundle::*;
+
+pub mod config;
+pub use config::*;
+
+pub mod counter_offer;
+pub use counter_offer::*;
+
+pub mod intent;
+pub use intent::*;
+
+pub mod milestone;
+pub use milestone::*;
+
+pub mod offer_book;
+pub use offer_book::*;
 
 #[account]
 #[derive(InitSpace)]
@@ -7,6 +37,127 @@ pub struct Escrow {
     pub maker: Pubkey,
     pub mint_a: Pubkey,
     pub mint_b: Pubkey,
-    pub receive: u64,
-    pub bump: u8
-}
\ No newline at end of file
+    pub receive: u64,                  // Token B still owed to the maker
+    pub offer: u64,                    // Token A still left in the vault for takers
+    pub expires_at: i64,               // Unix timestamp after which the offer can't be taken
+    pub taker: Option<Pubkey>,         // Only counterparty allowed to take a private offer
+    pub revision: u32,                 // Bumped by update_offer so takers can pin the terms seen
+    pub auction: Option<DutchAuction>, // Makes `receive` the starting price of a declining-price sale
+    pub book_page: u32,                // Page of the pair's offer book the escrow is listed on
+    pub hash_lock: Option<[u8; 32]>,   // SHA-256 of the secret the taker reveals to take
+    pub asset: Option<AssetLeg>,       // mpl-core asset traded on top of the token sides
+    // Holds the taker's payment until delivery is confirmed or ruled on
+    pub arbitration: Option<Arbitration>,
+    // Offered on top of token A, one vault per mint
+    #[max_len(MAX_BUNDLE_MINTS - 1)]
+    pub offer_legs: Vec<BundleLeg>,
+    // Owed on top of token B, paid to the maker on take
+    #[max_len(MAX_BUNDLE_MINTS - 1)]
+    pub receive_legs: Vec<BundleLeg>,
+    // Tranches of token A paid to the taker as each is signed off, instead of a take
+    #[max_len(MAX_MILESTONES)]
+    pub milestones: Vec<u64>,
+    pub released: u8,             // Number of tranches paid out so far
+    pub approver: Option<Pubkey>, // Can sign off milestones alongside the maker
+    pub bump: u8,
+}
+
+impl Escrow {
+    pub fn can_be_taken_by(&self, taker: &Pubkey) -> bool {
+        self.taker.is_none_or(|allowed| allowed == *taker)
+    }
+
+    pub fn matches_revision(&self, revision: Option<u32>) -> bool {
+        revision.is_none_or(|revision| revision == self.revision)
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        now >= self.expires_at
+    }
+
+    pub fn is_auction(&self) -> bool {
+        self.auction.is_some()
+    }
+
+    pub fn is_hash_locked(&self) -> bool {
+        self.hash_lock.is_some()
+    }
+
+    pub fn unlocks_with(&self, preimage: Option<&[u8]>) -> bool {
+        self.hash_lock
+            .is_none_or(|lock| preimage.is_some_and(|preimage| hash(preimage).to_bytes() == lock))
+    }
+
+    // A hash-locked offer stays put until it expires, so the taker can lock their side on the
+    // other chain without the maker pulling this one out from under them
+    pub fn can_be_refunded(&self, now: i64) -> bool {
+        !self.is_hash_locked() || self.is_expired(now)
+    }
+
+    // Bundles trade all their legs at once, so they can only be taken or refunded whole
+    pub fn is_bundle(&self) -> bool {
+        !self.offer_legs.is_empty() || !self.receive_legs.is_empty()
+    }
+
+    // Asset escrows move their asset along with the tokens, so they can only be taken whole too
+    pub fn is_asset(&self) -> bool {
+        self.asset.is_some()
+    }
+
+    pub fn is_arbitrated(&self) -> bool {
+        self.arbitration.is_some()
+    }
+
+    pub fn arbitration_status(&self) -> Option<ArbitrationStatus> {
+        self.arbitration.map(|arbitration| arbitration.status)
+    }
+
+    // Once an arbitrated escrow is taken both deposits are committed, and only a confirmation
+    // or a ruling can release them
+    pub fn is_arbitration_pending(&self) -> bool {
+        self.arbitration_status()
+            .is_some_and(|status| status != ArbitrationStatus::Open)
+    }
+
+    // Milestone escrows are paid out tranche by tranche, never taken. Refunding one only
+    // returns the tranches still in the vault, the released ones are the taker's for good
+    pub fn is_milestone(&self) -> bool {
+        !self.milestones.is_empty()
+    }
+
+    pub fn can_approve(&self, signer: &Pubkey) -> bool {
+        *signer == self.maker || self.approver == Some(*signer)
+    }
+
+    pub fn next_milestone(&self) -> Option<u64> {
+        self.milestones.get(self.released as usize).copied()
+    }
+
+    // Only public single-pair offers go on the book: a private one can only be filled by its
+    // taker, neither a bundle's nor an asset's price is a token A/B rate, and an arbitrated
+    // one is taken through its own instruction
+    pub fn is_listed(&self) -> bool {
+        self.taker.is_none() && !self.is_bundle() && !self.is_asset() && !self.is_arbitrated()
+    }
+
+    // Token B a full take costs at `now`
+    pub fn current_receive(&self, now: i64) -> u64 {
+        match self.auction {
+            Some(auction) => auction.price_at(self.receive, now),
+            None => self.receive,
+        }
+    }
+
+    // Token A released for `amount_b`, at the price set in `make`, rounded down in the maker's favor
+    pub fn offer_for(&self, amount_b: u64) -> Result<u64> {
+        require!(amount_b > 0, EscrowError::InvalidAmount);
+        require!(amount_b <= self.receive, EscrowError::ExceedsRemaining);
+
+        if amount_b == self.receive {
+            return Ok(self.offer);
+        }
+        let amount_a = self.offer as u128 * amount_b as u128 / self.receive as u128;
+        require!(amount_a > 0, EscrowError::InvalidAmount);
+        u64::try_from(amount_a).map_err(|_| EscrowError::Overflow.into())
+    }
+}
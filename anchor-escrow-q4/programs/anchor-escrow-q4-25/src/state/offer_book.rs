@@ -0,0 +1,94 @@
+use anchor_lang::prelude::*;
+
+use crate::{errors::EscrowError, DutchAuction, Escrow};
+
+// Offers listed per page. A full page makes room by dropping expired offers, and once
+// none are left makers list on the next page instead
+pub const BOOK_PAGE_ENTRIES: usize = 32;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct BookEntry {
+    pub escrow: Pubkey,
+    pub offer: u64,                    // Token A left in the escrow
+    pub receive: u64,                  // Token B asked for it, or a Dutch auction's start price
+    pub auction: Option<DutchAuction>, // Lets clients rank auctions by their current price
+    pub expires_at: i64,               // After this the entry may be dropped to make room
+}
+
+impl BookEntry {
+    // Token B the whole entry costs at `now`
+    pub fn receive_at(&self, now: i64) -> u64 {
+        match self.auction {
+            Some(auction) => auction.price_at(self.receive, now),
+            None => self.receive,
+        }
+    }
+}
+
+// Per mint pair: how many pages are open and the smallest offer that may be listed
+#[account]
+#[derive(InitSpace)]
+pub struct OfferBook {
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub min_offer: u64, // Set by the config authority so dust offers cannot flood the pages
+    pub pages: u32,
+    pub bump: u8,
+}
+
+impl OfferBook {
+    // A maker may list on any open page or open the next one
+    pub fn open_page(&mut self, page: u32) -> Result<()> {
+        require!(page <= self.pages, EscrowError::InvalidBookPage);
+        if page == self.pages {
+            self.pages = self.pages.checked_add(1).ok_or(EscrowError::Overflow)?;
+        }
+        Ok(())
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct BookPage {
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub page: u32,
+    #[max_len(BOOK_PAGE_ENTRIES)]
+    pub entries: Vec<BookEntry>, // Public open escrows, in listing order
+    pub bump: u8,
+}
+
+impl BookPage {
+    pub fn list(&mut self, key: Pubkey, escrow: &Escrow, now: i64) -> Result<()> {
+        if self.entries.len() == BOOK_PAGE_ENTRIES {
+            self.entries.retain(|entry| now < entry.expires_at);
+        }
+        require!(
+            self.entries.len() < BOOK_PAGE_ENTRIES,
+            EscrowError::BookPageFull
+        );
+        self.entries.push(BookEntry {
+            escrow: key,
+            offer: escrow.offer,
+            receive: escrow.receive,
+            auction: escrow.auction,
+            expires_at: escrow.expires_at,
+        });
+        Ok(())
+    }
+
+    // Reprices `escrow`'s entry to match its current terms, delisting it once emptied
+    pub fn sync(&mut self, key: Pubkey, escrow: &Escrow) {
+        if escrow.offer == 0 || escrow.receive == 0 {
+            return self.remove(&key);
+        }
+        if let Some(entry) = self.entries.iter_mut().find(|entry| entry.escrow == key) {
+            entry.offer = escrow.offer;
+            entry.receive = escrow.receive;
+        }
+    }
+
+    pub fn remove(&mut self, key: &Pubkey) {
+        self.entries.retain(|entry| entry.escrow != *key);
+    }
+}
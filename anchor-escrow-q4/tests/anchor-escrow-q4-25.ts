@@ -2,7 +2,8 @@ import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { AnchorEscrowQ425 } from "../target/types/anchor_escrow_q4_25";
 import { expect } from "chai";
-import { getAssociatedTokenAddressSync, createAssociatedTokenAccountInstruction, createMint, mintTo, TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { createHash, randomBytes } from "crypto";
+import { getAssociatedTokenAddressSync, createAssociatedTokenAccountInstruction, createMint, mintTo, approve, transfer, NATIVE_MINT, TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID } from "@solana/spl-token";
 
 describe("anchor_escrow_q4_25", () => {
   const provider = anchor.AnchorProvider.env();
@@ -28,6 +29,46 @@ describe("anchor_escrow_q4_25", () => {
   const depositAmount = 100;
   const receiveAmount = 200;
 
+  const feeBps = 100; // 1% marketplace fee, paid by the taker in mint B
+  const treasury = anchor.web3.Keypair.generate().publicKey;
+  let treasuryAtaB: anchor.web3.PublicKey;
+  let offerBook: anchor.web3.PublicKey;
+  let bookPage: anchor.web3.PublicKey;
+  const pageSeed = (page: number) => new anchor.BN(page).toArrayLike(Buffer, "le", 4);
+  const [config] = anchor.web3.PublicKey.findProgramAddressSync([Buffer.from("config")], program.programId);
+
+  // MakeParams with every optional mode unset, overridden per test
+  const makeParams = (params: Partial<{
+    taker: anchor.web3.PublicKey | null;
+    auction: any;
+    page: number;
+    offerLegs: { mint: anchor.web3.PublicKey; amount: anchor.BN }[];
+    receiveLegs: { mint: anchor.web3.PublicKey; amount: anchor.BN }[];
+    hashLock: number[] | null;
+    asset: { address: anchor.web3.PublicKey; offered: boolean } | null;
+    arbitration: { arbiter: anchor.web3.PublicKey; disputeWindow: anchor.BN } | null;
+    milestones: anchor.BN[];
+    approver: anchor.web3.PublicKey | null;
+  }> = {}) => ({
+    taker: null,
+    auction: null,
+    page: 0,
+    offerLegs: [],
+    receiveLegs: [],
+    hashLock: null,
+    asset: null,
+    arbitration: null,
+    milestones: [],
+    approver: null,
+    ...params,
+  });
+
+  // Expiry relative to the validator's clock, which can drift from the local one
+  const expiresIn = async (seconds: number) => {
+    const now = await provider.connection.getBlockTime(await provider.connection.getSlot());
+    return new anchor.BN(now + seconds);
+  };
+
   before(async () => {
     // Airdrop SOL to maker and taker
     await provider.connection.requestAirdrop(maker, 10 * anchor.web3.LAMPORTS_PER_SOL);
@@ -44,14 +85,38 @@ describe("anchor_escrow_q4_25", () => {
       createAssociatedTokenAccountInstruction(provider.wallet.publicKey, makerAtaA, maker, mintA)
     );
     await provider.sendAndConfirm(makerAtaATx);
-    await mintTo(provider.connection, provider.wallet.payer, mintA, makerAtaA, provider.wallet.payer, depositAmount * 2);
+    await mintTo(provider.connection, provider.wallet.payer, mintA, makerAtaA, provider.wallet.payer, depositAmount * 10);
 
     takerAtaB = getAssociatedTokenAddressSync(mintB, taker.publicKey);
     const takerAtaBTx = new anchor.web3.Transaction().add(
       createAssociatedTokenAccountInstruction(taker.publicKey, takerAtaB, taker.publicKey, mintB)
     );
     await provider.sendAndConfirm(takerAtaBTx, [taker]);
-    await mintTo(provider.connection, taker, mintB, takerAtaB, taker, receiveAmount * 2);
+    await mintTo(provider.connection, taker, mintB, takerAtaB, taker, receiveAmount * 50);
+
+    treasuryAtaB = getAssociatedTokenAddressSync(mintB, treasury);
+    [offerBook] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("book"), mintA.toBuffer(), mintB.toBuffer()],
+      program.programId
+    );
+    [bookPage] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("book_page"), mintA.toBuffer(), mintB.toBuffer(), pageSeed(0)],
+      program.programId
+    );
+    const [programData] = anchor.web3.PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      new anchor.web3.PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+    );
+    await program.methods
+      .setConfig(maker, treasury, feeBps)
+      .accountsStrict({
+        authority: maker,
+        config,
+        program: program.programId,
+        programData,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
 
   });
 
@@ -65,12 +130,14 @@ describe("anchor_escrow_q4_25", () => {
 
     // Make
     await program.methods
-      .make(seed1, new anchor.BN(depositAmount), new anchor.BN(receiveAmount))
+      .make(seed1, new anchor.BN(depositAmount), new anchor.BN(receiveAmount), await expiresIn(3600), makeParams())
       .accountsStrict({
         maker: maker,
         mintA: mintA,
         mintB: mintB,
         makerAtaA: makerAtaA,
+        offerBook,
+        bookPage,
         escrow: escrowPda,
         vault: vault,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
@@ -96,6 +163,7 @@ describe("anchor_escrow_q4_25", () => {
         maker: maker,
         mintA: mintA,
         makerAtaA: makerAtaA,
+        bookPage,
         escrow: escrowPda,
         vault: vault,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
@@ -122,12 +190,14 @@ describe("anchor_escrow_q4_25", () => {
 
     // Make (again for take path)
     await program.methods
-      .make(seed2, new anchor.BN(depositAmount), new anchor.BN(receiveAmount))
+      .make(seed2, new anchor.BN(depositAmount), new anchor.BN(receiveAmount), await expiresIn(3600), makeParams())
       .accountsStrict({
         maker: maker,
         mintA: mintA,
         mintB: mintB,
         makerAtaA: makerAtaA,
+        offerBook,
+        bookPage,
         escrow: escrowPda,
         vault: vault,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
@@ -142,7 +212,7 @@ describe("anchor_escrow_q4_25", () => {
 
     // Take
     await program.methods
-      .take()
+      .take(null, null)
       .accountsStrict({
         taker: taker.publicKey,
         maker: maker,
@@ -151,6 +221,10 @@ describe("anchor_escrow_q4_25", () => {
         takerAtaA: takerAtaA,
         takerAtaB: takerAtaB,
         makerAtaB: makerAtaB,
+        config,
+        treasury,
+        treasuryAtaB,
+        bookPage,
         escrow: escrowPda,
         vault: vault,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
@@ -173,5 +247,1859 @@ describe("anchor_escrow_q4_25", () => {
 
     const makerBalanceB = (await provider.connection.getTokenAccountBalance(makerAtaB)).value.uiAmount;
     expect(makerBalanceB).to.equal(receiveAmount);
+
+    const treasuryBalanceB = (await provider.connection.getTokenAccountBalance(treasuryAtaB)).value.uiAmount;
+    expect(treasuryBalanceB).to.equal(Math.ceil((receiveAmount * feeBps) / 10_000));
+  });
+
+  it("Leaves the treasury's token account alone on fee-free takes", async () => {
+    const [programData] = anchor.web3.PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      new anchor.web3.PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+    );
+    const setConfig = (wallet: anchor.web3.PublicKey, bps: number) =>
+      program.methods
+        .setConfig(maker, wallet, bps)
+        .accountsStrict({
+          authority: maker,
+          config,
+          program: program.programId,
+          programData,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+    // A treasury that has no mint B account yet, with the fee switched off
+    const freeTreasury = anchor.web3.Keypair.generate().publicKey;
+    const freeTreasuryAtaB = getAssociatedTokenAddressSync(mintB, freeTreasury);
+    await setConfig(freeTreasury, 0);
+
+    const seed = new anchor.BN(2323);
+    [escrowPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.toBuffer(), seed.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    vault = getAssociatedTokenAddressSync(mintA, escrowPda, true);
+    await program.methods
+      .make(seed, new anchor.BN(depositAmount), new anchor.BN(receiveAmount), await expiresIn(3600), makeParams())
+      .accountsStrict({
+        maker: maker,
+        mintA: mintA,
+        mintB: mintB,
+        makerAtaA: makerAtaA,
+        offerBook,
+        bookPage,
+        escrow: escrowPda,
+        vault: vault,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const takerBBefore = (await provider.connection.getTokenAccountBalance(takerAtaB)).value.uiAmount;
+    await program.methods
+      .take(null, null)
+      .accountsStrict({
+        taker: taker.publicKey,
+        maker: maker,
+        mintA: mintA,
+        mintB: mintB,
+        takerAtaA: takerAtaA,
+        takerAtaB: takerAtaB,
+        makerAtaB: makerAtaB,
+        config,
+        treasury: freeTreasury,
+        treasuryAtaB: freeTreasuryAtaB,
+        bookPage,
+        escrow: escrowPda,
+        vault: vault,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([taker])
+      .rpc();
+    await setConfig(treasury, feeBps);
+
+    const takerBAfter = (await provider.connection.getTokenAccountBalance(takerAtaB)).value.uiAmount;
+    expect(takerBBefore - takerBAfter).to.equal(receiveAmount);
+    expect(await provider.connection.getAccountInfo(freeTreasuryAtaB)).to.be.null;
+  });
+
+  it("Fills the escrow with partial takes", async () => {
+    const seed3 = new anchor.BN(3333);
+    [escrowPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.toBuffer(), seed3.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    vault = getAssociatedTokenAddressSync(mintA, escrowPda, true);
+
+    await program.methods
+      .make(seed3, new anchor.BN(depositAmount), new anchor.BN(receiveAmount), await expiresIn(3600), makeParams())
+      .accountsStrict({
+        maker: maker,
+        mintA: mintA,
+        mintB: mintB,
+        makerAtaA: makerAtaA,
+        offerBook,
+        bookPage,
+        escrow: escrowPda,
+        vault: vault,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const takePartial = (amountB: number) =>
+      program.methods
+        .takePartial(new anchor.BN(amountB), null)
+        .accountsStrict({
+          taker: taker.publicKey,
+          maker: maker,
+          mintA: mintA,
+          mintB: mintB,
+          takerAtaA: takerAtaA,
+          takerAtaB: takerAtaB,
+          makerAtaB: makerAtaB,
+          config,
+          treasury,
+          treasuryAtaB,
+          bookPage,
+          escrow: escrowPda,
+          vault: vault,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([taker])
+        .rpc();
+
+    const takerBalanceBefore = (await provider.connection.getTokenAccountBalance(takerAtaA)).value.uiAmount;
+    const treasuryBefore = (await provider.connection.getTokenAccountBalance(treasuryAtaB)).value.uiAmount;
+
+    // A quarter of the requested amount releases a quarter of the offer
+    await takePartial(receiveAmount / 4);
+
+    // 1% of 50 rounds up, so small fills still pay the marketplace fee
+    const treasuryAfter = (await provider.connection.getTokenAccountBalance(treasuryAtaB)).value.uiAmount;
+    expect(treasuryAfter - treasuryBefore).to.equal(Math.ceil(((receiveAmount / 4) * feeBps) / 10_000));
+
+    const escrowAccount = await program.account.escrow.fetch(escrowPda);
+    expect(escrowAccount.receive.toNumber()).to.equal((receiveAmount * 3) / 4);
+    expect(escrowAccount.offer.toNumber()).to.equal((depositAmount * 3) / 4);
+    const vaultBalance = (await provider.connection.getTokenAccountBalance(vault)).value.uiAmount;
+    expect(vaultBalance).to.equal((depositAmount * 3) / 4);
+
+    // A donation to the vault can't keep the last fill from closing it
+    const donation = 5;
+    await transfer(provider.connection, provider.wallet.payer, makerAtaA, vault, provider.wallet.payer, donation);
+
+    // The rest fills the escrow and closes it, the donation going to the last taker
+    await takePartial((receiveAmount * 3) / 4);
+
+    expect(await provider.connection.getAccountInfo(escrowPda)).to.be.null;
+    expect(await provider.connection.getAccountInfo(vault)).to.be.null;
+
+    const takerBalanceAfter = (await provider.connection.getTokenAccountBalance(takerAtaA)).value.uiAmount;
+    expect(takerBalanceAfter - takerBalanceBefore).to.equal(depositAmount + donation);
+  });
+
+  it("Lets anyone clean up an expired escrow", async () => {
+    const seed4 = new anchor.BN(4444);
+    [escrowPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.toBuffer(), seed4.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    vault = getAssociatedTokenAddressSync(mintA, escrowPda, true);
+
+    await program.methods
+      .make(seed4, new anchor.BN(depositAmount), new anchor.BN(receiveAmount), await expiresIn(2), makeParams())
+      .accountsStrict({
+        maker: maker,
+        mintA: mintA,
+        mintB: mintB,
+        makerAtaA: makerAtaA,
+        offerBook,
+        bookPage,
+        escrow: escrowPda,
+        vault: vault,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const expire = () =>
+      program.methods
+        .expire()
+        .accountsStrict({
+          cranker: taker.publicKey,
+          maker: maker,
+          mintA: mintA,
+          makerAtaA: makerAtaA,
+          bookPage,
+          escrow: escrowPda,
+          vault: vault,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([taker])
+        .rpc();
+
+    try {
+      await expire();
+      expect.fail("expire should fail before the deadline");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("NotExpired");
+    }
+
+    await new Promise(resolve => setTimeout(resolve, 4000));
+
+    const makerBalanceBefore = (await provider.connection.getTokenAccountBalance(makerAtaA)).value.uiAmount;
+    const crankerLamportsBefore = await provider.connection.getBalance(taker.publicKey);
+    await expire();
+
+    expect(await provider.connection.getAccountInfo(escrowPda)).to.be.null;
+    expect(await provider.connection.getAccountInfo(vault)).to.be.null;
+
+    const makerBalanceAfter = (await provider.connection.getTokenAccountBalance(makerAtaA)).value.uiAmount;
+    expect(makerBalanceAfter - makerBalanceBefore).to.equal(depositAmount);
+
+    // The vault's rent more than covers the cranker's transaction fee
+    const crankerLamportsAfter = await provider.connection.getBalance(taker.publicKey);
+    expect(crankerLamportsAfter).to.be.greaterThan(crankerLamportsBefore);
+    // Expiring takes the offer off the book too
+    const entries = (await program.account.bookPage.fetch(bookPage)).entries.map((entry) => entry.escrow.toBase58());
+    expect(entries).to.not.include(escrowPda.toBase58());
+  });
+
+  it("Reserves a private escrow for the named taker", async () => {
+    const seed5 = new anchor.BN(5555);
+    [escrowPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.toBuffer(), seed5.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    vault = getAssociatedTokenAddressSync(mintA, escrowPda, true);
+    const namedTaker = anchor.web3.Keypair.generate().publicKey;
+
+    const makePrivate = async (book: { offerBook: anchor.web3.PublicKey | null; bookPage: anchor.web3.PublicKey | null }) =>
+      program.methods
+        .make(seed5, new anchor.BN(depositAmount), new anchor.BN(receiveAmount), await expiresIn(3600), makeParams({ taker: namedTaker }))
+        .accountsStrict({
+          maker: maker,
+          mintA: mintA,
+          mintB: mintB,
+          makerAtaA: makerAtaA,
+          ...book,
+          escrow: escrowPda,
+          vault: vault,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+    // A private offer never goes on the book, so the maker doesn't pay for a page
+    try {
+      await makePrivate({ offerBook, bookPage });
+      expect.fail("make should reject book accounts for a private escrow");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidBookAccounts");
+    }
+    await makePrivate({ offerBook: null, bookPage: null });
+
+    const escrowAccount = await program.account.escrow.fetch(escrowPda);
+    expect(escrowAccount.taker.toBase58()).to.equal(namedTaker.toBase58());
+
+    try {
+      await program.methods
+        .take(null, null)
+        .accountsStrict({
+          taker: taker.publicKey,
+          maker: maker,
+          mintA: mintA,
+          mintB: mintB,
+          takerAtaA: takerAtaA,
+          takerAtaB: takerAtaB,
+          makerAtaB: makerAtaB,
+          config,
+          treasury,
+          treasuryAtaB,
+          bookPage: null,
+          escrow: escrowPda,
+          vault: vault,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([taker])
+        .rpc();
+      expect.fail("take should fail for anyone but the named taker");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidTaker");
+    }
+
+    await program.methods
+      .refund()
+      .accountsStrict({
+        maker: maker,
+        mintA: mintA,
+        makerAtaA: makerAtaA,
+        bookPage: null,
+        escrow: escrowPda,
+        vault: vault,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+  });
+
+  it("Updates the offer and lets takers pin the revision", async () => {
+    const seed6 = new anchor.BN(6666);
+    [escrowPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.toBuffer(), seed6.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    vault = getAssociatedTokenAddressSync(mintA, escrowPda, true);
+
+    await program.methods
+      .make(seed6, new anchor.BN(depositAmount), new anchor.BN(receiveAmount), await expiresIn(3600), makeParams())
+      .accountsStrict({
+        maker: maker,
+        mintA: mintA,
+        mintB: mintB,
+        makerAtaA: makerAtaA,
+        offerBook,
+        bookPage,
+        escrow: escrowPda,
+        vault: vault,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    // Top the vault up and ask for more in return
+    const newOffer = depositAmount + 50;
+    const newReceive = receiveAmount + 50;
+    await program.methods
+      .updateOffer(new anchor.BN(newOffer), new anchor.BN(newReceive))
+      .accountsStrict({
+        maker: maker,
+        mintA: mintA,
+        makerAtaA: makerAtaA,
+        offerBook,
+        bookPage,
+        escrow: escrowPda,
+        vault: vault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+
+    const escrowAccount = await program.account.escrow.fetch(escrowPda);
+    expect(escrowAccount.offer.toNumber()).to.equal(newOffer);
+    expect(escrowAccount.receive.toNumber()).to.equal(newReceive);
+    expect(escrowAccount.revision).to.equal(1);
+    const vaultBalance = (await provider.connection.getTokenAccountBalance(vault)).value.uiAmount;
+    expect(vaultBalance).to.equal(newOffer);
+
+    const take = (revision: number) =>
+      program.methods
+        .take(revision, null)
+        .accountsStrict({
+          taker: taker.publicKey,
+          maker: maker,
+          mintA: mintA,
+          mintB: mintB,
+          takerAtaA: takerAtaA,
+          takerAtaB: takerAtaB,
+          makerAtaB: makerAtaB,
+          config,
+          treasury,
+          treasuryAtaB,
+          bookPage,
+          escrow: escrowPda,
+          vault: vault,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([taker])
+        .rpc();
+
+    // A taker who saw the original terms is protected from the change
+    try {
+      await take(0);
+      expect.fail("take should fail on a stale revision");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("RevisionMismatch");
+    }
+
+    await take(1);
+    expect(await provider.connection.getAccountInfo(escrowPda)).to.be.null;
+  });
+
+  const CORE_PROGRAM_ID = new anchor.web3.PublicKey("CoREENxT6tW1HoK8ypY1SxRMZTcVPm7R94rH4PZNhX7d");
+
+  // Minimal core CreateV1 (no collection, no plugins), encoded by hand to avoid pulling in umi
+  const createAsset = async (owner: anchor.web3.PublicKey) => {
+    const asset = anchor.web3.Keypair.generate();
+    const str = (s: string) => {
+      const len = Buffer.alloc(4);
+      len.writeUInt32LE(s.length);
+      return Buffer.concat([len, Buffer.from(s)]);
+    };
+    const data = Buffer.concat([
+      Buffer.from([0, 0]), // CreateV1 discriminator, DataState::AccountState
+      str("Escrow Test"),
+      str("https://example.com/asset.json"),
+      Buffer.from([0]), // no plugins
+    ]);
+    const ix = new anchor.web3.TransactionInstruction({
+      programId: CORE_PROGRAM_ID,
+      keys: [
+        { pubkey: asset.publicKey, isSigner: true, isWritable: true },
+        { pubkey: CORE_PROGRAM_ID, isSigner: false, isWritable: false }, // collection
+        { pubkey: CORE_PROGRAM_ID, isSigner: false, isWritable: false }, // authority
+        { pubkey: maker, isSigner: true, isWritable: true },
+        { pubkey: owner, isSigner: false, isWritable: false },
+        { pubkey: CORE_PROGRAM_ID, isSigner: false, isWritable: false }, // update authority
+        { pubkey: anchor.web3.SystemProgram.programId, isSigner: false, isWritable: false },
+        { pubkey: CORE_PROGRAM_ID, isSigner: false, isWritable: false }, // log wrapper
+      ],
+      data,
+    });
+    await provider.sendAndConfirm(new anchor.web3.Transaction().add(ix), [asset]);
+    return asset.publicKey;
+  };
+
+  // Owner pubkey sits after the key byte in every core asset
+  const assetOwner = async (asset: anchor.web3.PublicKey) => {
+    const info = await provider.connection.getAccountInfo(asset);
+    return new anchor.web3.PublicKey(info.data.subarray(1, 33));
+  };
+
+  // Asset escrows trade through the regular instructions, passing the asset's accounts where a
+  // bundle passes its legs
+  const assetAccounts = (asset: anchor.web3.PublicKey) => [
+    { pubkey: asset, isSigner: false, isWritable: true },
+    { pubkey: CORE_PROGRAM_ID, isSigner: false, isWritable: false },
+  ];
+  const assetEscrowFor = (seed: anchor.BN) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.toBuffer(), seed.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+  const makeAssetEscrow = async (
+    seed: anchor.BN,
+    offer: number,
+    receive: number,
+    params: object,
+    remainingAccounts: anchor.web3.AccountMeta[]
+  ) =>
+    program.methods
+      .make(seed, new anchor.BN(offer), new anchor.BN(receive), await expiresIn(3600), makeParams(params))
+      .accountsStrict({
+        maker,
+        mintA,
+        mintB,
+        makerAtaA,
+        escrow: assetEscrowFor(seed),
+        offerBook: null,
+        bookPage: null,
+        vault: getAssociatedTokenAddressSync(mintA, assetEscrowFor(seed), true),
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .remainingAccounts(remainingAccounts)
+      .rpc();
+  const takeAssetEscrow = (seed: anchor.BN) =>
+    program.methods
+      .take(null, null)
+      .accountsStrict({
+        taker: taker.publicKey,
+        maker,
+        mintA,
+        mintB,
+        takerAtaA,
+        takerAtaB,
+        makerAtaB,
+        config,
+        treasury,
+        treasuryAtaB,
+        bookPage: null,
+        escrow: assetEscrowFor(seed),
+        vault: getAssociatedTokenAddressSync(mintA, assetEscrowFor(seed), true),
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([taker]);
+
+  it("Sells a core asset for tokens", async () => {
+    const asset = await createAsset(maker);
+    const seed7 = new anchor.BN(7777);
+
+    // The asset takes token A's place, so the maker offers none of it
+    await makeAssetEscrow(seed7, 0, receiveAmount, { asset: { address: asset, offered: true } }, assetAccounts(asset));
+    expect((await assetOwner(asset)).toBase58()).to.equal(assetEscrowFor(seed7).toBase58());
+    expect((await program.account.escrow.fetch(assetEscrowFor(seed7))).asset.offered).to.be.true;
+
+    const makerBefore = (await provider.connection.getTokenAccountBalance(makerAtaB)).value.uiAmount;
+    const treasuryBefore = (await provider.connection.getTokenAccountBalance(treasuryAtaB)).value.uiAmount;
+    await takeAssetEscrow(seed7).remainingAccounts(assetAccounts(asset)).rpc();
+
+    expect((await assetOwner(asset)).toBase58()).to.equal(taker.publicKey.toBase58());
+    const makerAfter = (await provider.connection.getTokenAccountBalance(makerAtaB)).value.uiAmount;
+    expect(makerAfter - makerBefore).to.equal(receiveAmount);
+    // Like every take, the taker pays the marketplace fee on top of the price in mint B
+    const treasuryAfter = (await provider.connection.getTokenAccountBalance(treasuryAtaB)).value.uiAmount;
+    expect(treasuryAfter - treasuryBefore).to.equal(Math.ceil((receiveAmount * feeBps) / 10_000));
+    expect(await provider.connection.getAccountInfo(assetEscrowFor(seed7))).to.be.null;
+  });
+
+  it("Bids tokens for a core asset and refunds an asset listing", async () => {
+    const asset = await createAsset(taker.publicKey);
+    const seed8 = new anchor.BN(8888);
+
+    // The bid pays token A for the asset, which stands in for token B
+    await makeAssetEscrow(
+      seed8,
+      depositAmount,
+      0,
+      { asset: { address: asset, offered: false } },
+      assetAccounts(asset).slice(0, 1)
+    );
+
+    const takerBefore = (await provider.connection.getTokenAccountBalance(takerAtaA)).value.uiAmount;
+    await takeAssetEscrow(seed8).remainingAccounts(assetAccounts(asset)).rpc();
+
+    expect((await assetOwner(asset)).toBase58()).to.equal(maker.toBase58());
+    const takerAfter = (await provider.connection.getTokenAccountBalance(takerAtaA)).value.uiAmount;
+    expect(takerAfter - takerBefore).to.equal(depositAmount);
+    expect(await provider.connection.getAccountInfo(assetEscrowFor(seed8))).to.be.null;
+
+    // The maker now owns the asset and can list it privately, then change their mind
+    const seed9 = new anchor.BN(9999);
+    const namedTaker = anchor.web3.Keypair.generate().publicKey;
+    await makeAssetEscrow(
+      seed9,
+      0,
+      receiveAmount,
+      { taker: namedTaker, asset: { address: asset, offered: true } },
+      assetAccounts(asset)
+    );
+    try {
+      await takeAssetEscrow(seed9).remainingAccounts(assetAccounts(asset)).rpc();
+      expect.fail("take should be reserved for the named taker");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidTaker");
+    }
+
+    await program.methods
+      .refund()
+      .accountsStrict({
+        maker,
+        mintA,
+        makerAtaA,
+        escrow: assetEscrowFor(seed9),
+        bookPage: null,
+        vault: getAssociatedTokenAddressSync(mintA, assetEscrowFor(seed9), true),
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .remainingAccounts(assetAccounts(asset))
+      .rpc();
+
+    expect((await assetOwner(asset)).toBase58()).to.equal(maker.toBase58());
+    expect(await provider.connection.getAccountInfo(assetEscrowFor(seed9))).to.be.null;
+  });
+
+  it("Trades a bundle of mints atomically", async () => {
+    // Offered alongside mint A, and asked for alongside mint B
+    const mintC = await createMint(provider.connection, provider.wallet.payer, maker, null, 0);
+    const mintD = await createMint(provider.connection, provider.wallet.payer, taker.publicKey, null, 0);
+    const makerAtaC = getAssociatedTokenAddressSync(mintC, maker);
+    const takerAtaD = getAssociatedTokenAddressSync(mintD, taker.publicKey);
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        createAssociatedTokenAccountInstruction(maker, makerAtaC, maker, mintC),
+        createAssociatedTokenAccountInstruction(maker, takerAtaD, taker.publicKey, mintD)
+      )
+    );
+    await mintTo(provider.connection, provider.wallet.payer, mintC, makerAtaC, provider.wallet.payer, depositAmount * 2);
+    await mintTo(provider.connection, taker, mintD, takerAtaD, taker, receiveAmount * 2);
+
+    const meta = (pubkey: anchor.web3.PublicKey, isWritable = true) => ({ pubkey, isSigner: false, isWritable });
+    const makeBundle = async (seed: anchor.BN) => {
+      const [escrow] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), maker.toBuffer(), seed.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+      const vaultA = getAssociatedTokenAddressSync(mintA, escrow, true);
+      const vaultC = getAssociatedTokenAddressSync(mintC, escrow, true);
+      await program.methods
+        .make(
+          seed,
+          new anchor.BN(depositAmount),
+          new anchor.BN(receiveAmount),
+          await expiresIn(3600),
+          makeParams({
+            offerLegs: [{ mint: mintC, amount: new anchor.BN(depositAmount) }],
+            receiveLegs: [{ mint: mintD, amount: new anchor.BN(receiveAmount) }],
+          })
+        )
+        .accountsStrict({
+          maker,
+          mintA,
+          mintB,
+          makerAtaA,
+          offerBook: null,
+          bookPage: null,
+          escrow,
+          vault: vaultA,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .remainingAccounts([meta(mintC, false), meta(makerAtaC), meta(vaultC)])
+        .rpc();
+      return { escrow, vaultA, vaultC };
+    };
+
+    const bundle = await makeBundle(new anchor.BN(10101));
+    expect((await provider.connection.getTokenAccountBalance(bundle.vaultA)).value.uiAmount).to.equal(depositAmount);
+    expect((await provider.connection.getTokenAccountBalance(bundle.vaultC)).value.uiAmount).to.equal(depositAmount);
+    const bundleAccount = await program.account.escrow.fetch(bundle.escrow);
+    expect(bundleAccount.offerLegs.map((leg) => leg.mint.toBase58())).to.deep.equal([mintC.toBase58()]);
+    expect(bundleAccount.receiveLegs.map((leg) => leg.mint.toBase58())).to.deep.equal([mintD.toBase58()]);
+    // Bundles are not priced as a token A/B rate, so they stay off the book and its accounts
+    expect(bundleAccount.bookPage).to.equal(0);
+
+    const takerAtaC = getAssociatedTokenAddressSync(mintC, taker.publicKey);
+    const makerAtaD = getAssociatedTokenAddressSync(mintD, maker);
+    const treasuryAtaD = getAssociatedTokenAddressSync(mintD, treasury);
+    const takerABefore = (await provider.connection.getTokenAccountBalance(takerAtaA)).value.uiAmount;
+    await program.methods
+      .take(null, null)
+      .accountsStrict({
+        taker: taker.publicKey,
+        maker,
+        mintA,
+        mintB,
+        takerAtaA,
+        takerAtaB,
+        makerAtaB,
+        config,
+        treasury,
+        treasuryAtaB,
+        bookPage: null,
+        escrow: bundle.escrow,
+        vault: bundle.vaultA,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .remainingAccounts([
+        meta(mintD, false), meta(takerAtaD), meta(makerAtaD), meta(treasuryAtaD),
+        meta(mintC, false), meta(bundle.vaultC), meta(takerAtaC),
+      ])
+      .signers([taker])
+      .rpc();
+
+    const takerAAfter = (await provider.connection.getTokenAccountBalance(takerAtaA)).value.uiAmount;
+    expect(takerAAfter - takerABefore).to.equal(depositAmount);
+    expect((await provider.connection.getTokenAccountBalance(takerAtaC)).value.uiAmount).to.equal(depositAmount);
+    expect((await provider.connection.getTokenAccountBalance(makerAtaD)).value.uiAmount).to.equal(receiveAmount);
+    expect((await provider.connection.getTokenAccountBalance(treasuryAtaD)).value.uiAmount).to.equal(
+      Math.ceil((receiveAmount * feeBps) / 10_000)
+    );
+    expect(await provider.connection.getAccountInfo(bundle.escrow)).to.be.null;
+    expect(await provider.connection.getAccountInfo(bundle.vaultA)).to.be.null;
+    expect(await provider.connection.getAccountInfo(bundle.vaultC)).to.be.null;
+
+    // A refund hands back every offered leg
+    const refunded = await makeBundle(new anchor.BN(10102));
+    const makerCBefore = (await provider.connection.getTokenAccountBalance(makerAtaC)).value.uiAmount;
+    await program.methods
+      .refund()
+      .accountsStrict({
+        maker,
+        mintA,
+        makerAtaA,
+        bookPage: null,
+        escrow: refunded.escrow,
+        vault: refunded.vaultA,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .remainingAccounts([meta(mintC, false), meta(refunded.vaultC), meta(makerAtaC)])
+      .rpc();
+
+    const makerCAfter = (await provider.connection.getTokenAccountBalance(makerAtaC)).value.uiAmount;
+    expect(makerCAfter - makerCBefore).to.equal(depositAmount);
+    expect(await provider.connection.getAccountInfo(refunded.escrow)).to.be.null;
+    expect(await provider.connection.getAccountInfo(refunded.vaultC)).to.be.null;
+  });
+
+  it("Trades native SOL against tokens in both directions", async () => {
+    const lamports = anchor.web3.LAMPORTS_PER_SOL / 2;
+    const escrowFor = (seed: anchor.BN) =>
+      anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), maker.toBuffer(), seed.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      )[0];
+    const bookFor = (a: anchor.web3.PublicKey, b: anchor.web3.PublicKey) => ({
+      offerBook: anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("book"), a.toBuffer(), b.toBuffer()],
+        program.programId
+      )[0],
+      bookPage: anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("book_page"), a.toBuffer(), b.toBuffer(), pageSeed(0)],
+        program.programId
+      )[0],
+    });
+    // Native SOL sides use the wrapped SOL mint, leaving out the wallets' own token accounts
+    const make = async (seed: anchor.BN, a: anchor.web3.PublicKey, b: anchor.web3.PublicKey, offer: number, receive: number) =>
+      program.methods
+        .make(seed, new anchor.BN(offer), new anchor.BN(receive), await expiresIn(3600), makeParams())
+        .accountsStrict({
+          maker,
+          mintA: a,
+          mintB: b,
+          makerAtaA: a.equals(NATIVE_MINT) ? null : getAssociatedTokenAddressSync(a, maker),
+          escrow: escrowFor(seed),
+          ...bookFor(a, b),
+          vault: getAssociatedTokenAddressSync(a, escrowFor(seed), true),
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+    const take = (seed: anchor.BN, a: anchor.web3.PublicKey, b: anchor.web3.PublicKey) =>
+      program.methods
+        .take(null, null)
+        .accountsStrict({
+          taker: taker.publicKey,
+          maker,
+          mintA: a,
+          mintB: b,
+          takerAtaA: a.equals(NATIVE_MINT) ? null : getAssociatedTokenAddressSync(a, taker.publicKey),
+          takerAtaB: b.equals(NATIVE_MINT) ? null : getAssociatedTokenAddressSync(b, taker.publicKey),
+          makerAtaB: b.equals(NATIVE_MINT) ? null : getAssociatedTokenAddressSync(b, maker),
+          config,
+          treasury,
+          treasuryAtaB: getAssociatedTokenAddressSync(b, treasury),
+          bookPage: bookFor(a, b).bookPage,
+          escrow: escrowFor(seed),
+          vault: getAssociatedTokenAddressSync(a, escrowFor(seed), true),
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([taker])
+        .rpc();
+
+    // Maker sells SOL for mint B, the taker is paid in lamports, vault rent included
+    const seed11 = new anchor.BN(11111);
+    await make(seed11, NATIVE_MINT, mintB, lamports, receiveAmount);
+    const solVault = getAssociatedTokenAddressSync(NATIVE_MINT, escrowFor(seed11), true);
+    expect(Number((await provider.connection.getTokenAccountBalance(solVault)).value.amount)).to.equal(lamports);
+
+    // The taker also pays the transaction fee, so only check the bulk of the SOL arrived
+    const takerSolBefore = await provider.connection.getBalance(taker.publicKey);
+    await take(seed11, NATIVE_MINT, mintB);
+    const takerSolAfter = await provider.connection.getBalance(taker.publicKey);
+    expect(takerSolAfter - takerSolBefore).to.be.greaterThan(lamports - 10_000);
+    expect(await provider.connection.getAccountInfo(escrowFor(seed11))).to.be.null;
+    expect(await provider.connection.getAccountInfo(solVault)).to.be.null;
+
+    // Maker sells mint A for SOL, paid in lamports along with the fee to the treasury wallet
+    const seed12 = new anchor.BN(12121);
+    await make(seed12, mintA, NATIVE_MINT, depositAmount, lamports);
+    const makerSolBefore = await provider.connection.getBalance(maker);
+    const treasurySolBefore = await provider.connection.getBalance(treasury);
+    await take(seed12, mintA, NATIVE_MINT);
+    expect((await provider.connection.getBalance(maker)) - makerSolBefore).to.be.at.least(lamports);
+    expect((await provider.connection.getBalance(treasury)) - treasurySolBefore).to.equal(
+      Math.ceil((lamports * feeBps) / 10_000)
+    );
+    expect(await provider.connection.getAccountInfo(getAssociatedTokenAddressSync(NATIVE_MINT, maker))).to.be.null;
+
+    // Refunding a SOL offer without a token account hands the lamports straight back
+    const seed13 = new anchor.BN(13131);
+    await make(seed13, NATIVE_MINT, mintB, lamports, receiveAmount);
+    const refundVault = getAssociatedTokenAddressSync(NATIVE_MINT, escrowFor(seed13), true);
+    const refundedBefore = await provider.connection.getBalance(maker);
+    await program.methods
+      .refund()
+      .accountsStrict({
+        maker,
+        mintA: NATIVE_MINT,
+        makerAtaA: null,
+        escrow: escrowFor(seed13),
+        bookPage: bookFor(NATIVE_MINT, mintB).bookPage,
+        vault: refundVault,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    expect((await provider.connection.getBalance(maker)) - refundedBefore).to.be.greaterThan(lamports);
+    expect(await provider.connection.getAccountInfo(refundVault)).to.be.null;
+
+    // Only a native SOL side can go without token accounts
+    try {
+      await program.methods
+        .make(new anchor.BN(14141), new anchor.BN(depositAmount), new anchor.BN(receiveAmount), await expiresIn(3600), makeParams())
+        .accountsStrict({
+          maker,
+          mintA,
+          mintB,
+          makerAtaA: null,
+          escrow: escrowFor(new anchor.BN(14141)),
+          offerBook,
+          bookPage,
+          vault: getAssociatedTokenAddressSync(mintA, escrowFor(new anchor.BN(14141)), true),
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+      expect.fail("make should require a token account for a non-native mint");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidNativeAccounts");
+    }
+  });
+
+  it("Holds both sides of an arbitrated escrow until delivery or a ruling", async () => {
+    const arbiter = anchor.web3.Keypair.generate();
+    const arbitrated = (seed: anchor.BN) => {
+      const [escrow] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), maker.toBuffer(), seed.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+      return {
+        escrow,
+        vault: getAssociatedTokenAddressSync(mintA, escrow, true),
+        vaultB: getAssociatedTokenAddressSync(mintB, escrow, true),
+      };
+    };
+    const makeAndTake = async (seed: anchor.BN, disputeWindow: number) => {
+      const { escrow, vault, vaultB } = arbitrated(seed);
+      await program.methods
+        .make(
+          seed,
+          new anchor.BN(depositAmount),
+          new anchor.BN(receiveAmount),
+          await expiresIn(3600),
+          makeParams({ arbitration: { arbiter: arbiter.publicKey, disputeWindow: new anchor.BN(disputeWindow) } })
+        )
+        .accountsStrict({
+          maker,
+          mintA,
+          mintB,
+          makerAtaA,
+          escrow,
+          offerBook: null,
+          bookPage: null,
+          vault,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+      await program.methods
+        .takeArbitrated()
+        .accountsStrict({
+          taker: taker.publicKey,
+          maker,
+          mintB,
+          takerAtaB,
+          config,
+          escrow,
+          vaultB,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([taker])
+        .rpc();
+    };
+    const settleAccounts = (seed: anchor.BN, signer: anchor.web3.PublicKey) => {
+      const { escrow, vault, vaultB } = arbitrated(seed);
+      return {
+        signer,
+        maker,
+        taker: taker.publicKey,
+        mintA,
+        mintB,
+        makerAtaA,
+        makerAtaB,
+        takerAtaA,
+        takerAtaB,
+        config,
+        treasury,
+        treasuryAtaB,
+        escrow,
+        vault,
+        vaultB,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      };
+    };
+    const dispute = (seed: anchor.BN) =>
+      program.methods
+        .disputeArbitrated()
+        .accountsStrict({ signer: taker.publicKey, escrow: arbitrated(seed).escrow })
+        .signers([taker])
+        .rpc();
+    const balance = async (ata: anchor.web3.PublicKey) =>
+      (await provider.connection.getTokenAccountBalance(ata)).value.uiAmount;
+    const arbitratedFee = Math.ceil((receiveAmount * feeBps) / 10_000);
+
+    // Maker confirms delivery and the trade completes, the fee waits in the vault until then
+    const seed23 = new anchor.BN(23031);
+    await makeAndTake(seed23, 3600);
+    expect(await balance(arbitrated(seed23).vaultB)).to.equal(receiveAmount + arbitratedFee);
+
+    // A taken escrow can't be pulled back by the maker
+    try {
+      await program.methods
+        .refund()
+        .accountsStrict({
+          maker,
+          mintA,
+          makerAtaA,
+          escrow: arbitrated(seed23).escrow,
+          bookPage: null,
+          vault: arbitrated(seed23).vault,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+      expect.fail("refund should fail once taken");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidStatus");
+    }
+
+    const makerBBefore = await balance(makerAtaB);
+    const takerABefore = await balance(takerAtaA);
+    const treasuryBefore = await balance(treasuryAtaB);
+    await program.methods.confirmArbitrated().accountsStrict(settleAccounts(seed23, maker)).rpc();
+    expect((await balance(makerAtaB)) - makerBBefore).to.equal(receiveAmount);
+    expect((await balance(treasuryAtaB)) - treasuryBefore).to.equal(arbitratedFee);
+    expect((await balance(takerAtaA)) - takerABefore).to.equal(depositAmount);
+    expect(await provider.connection.getAccountInfo(arbitrated(seed23).escrow)).to.be.null;
+
+    // Taker disputes and the arbiter unwinds the trade
+    const seed24 = new anchor.BN(23032);
+    await makeAndTake(seed24, 3600);
+    await dispute(seed24);
+
+    try {
+      await program.methods.confirmArbitrated().accountsStrict(settleAccounts(seed24, maker)).rpc();
+      expect.fail("confirm should fail once disputed");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidStatus");
+    }
+    try {
+      await program.methods.resolveArbitrated(false).accountsStrict(settleAccounts(seed24, maker)).rpc();
+      expect.fail("only the arbiter can rule before the ruling is overdue");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("RulingPending");
+    }
+
+    const makerABefore = await balance(makerAtaA);
+    const takerBBefore = await balance(takerAtaB);
+    await program.methods
+      .resolveArbitrated(false)
+      .accountsStrict(settleAccounts(seed24, arbiter.publicKey))
+      .signers([arbiter])
+      .rpc();
+    expect((await balance(makerAtaA)) - makerABefore).to.equal(depositAmount);
+    // An unwound trade refunds the fee along with the payment
+    expect((await balance(takerAtaB)) - takerBBefore).to.equal(receiveAmount + arbitratedFee);
+    expect(await provider.connection.getAccountInfo(arbitrated(seed24).escrow)).to.be.null;
+  });
+
+  it("Lets either side unwind a dispute the arbiter never rules on", async () => {
+    const arbiter = anchor.web3.Keypair.generate();
+    const seed = new anchor.BN(23033);
+    const [escrow] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.toBuffer(), seed.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const vault = getAssociatedTokenAddressSync(mintA, escrow, true);
+    const vaultB = getAssociatedTokenAddressSync(mintB, escrow, true);
+
+    await program.methods
+      .make(
+        seed,
+        new anchor.BN(depositAmount),
+        new anchor.BN(receiveAmount),
+        await expiresIn(3600),
+        makeParams({ arbitration: { arbiter: arbiter.publicKey, disputeWindow: new anchor.BN(2) } })
+      )
+      .accountsStrict({
+        maker,
+        mintA,
+        mintB,
+        makerAtaA,
+        escrow,
+        offerBook: null,
+        bookPage: null,
+        vault,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    await program.methods
+      .takeArbitrated()
+      .accountsStrict({
+        taker: taker.publicKey,
+        maker,
+        mintB,
+        takerAtaB,
+        config,
+        escrow,
+        vaultB,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([taker])
+      .rpc();
+    await program.methods
+      .disputeArbitrated()
+      .accountsStrict({ signer: taker.publicKey, escrow })
+      .signers([taker])
+      .rpc();
+
+    const resolve = (completeTrade: boolean) =>
+      program.methods
+        .resolveArbitrated(completeTrade)
+        .accountsStrict({
+          signer: taker.publicKey,
+          maker,
+          taker: taker.publicKey,
+          mintA,
+          mintB,
+          makerAtaA,
+          makerAtaB,
+          takerAtaA,
+          takerAtaB,
+          config,
+          treasury,
+          treasuryAtaB,
+          escrow,
+          vault,
+          vaultB,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([taker])
+        .rpc();
+
+    try {
+      await resolve(false);
+      expect.fail("the arbiter still has time to rule");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("RulingPending");
+    }
+
+    await new Promise(resolve => setTimeout(resolve, 4000));
+
+    // Only the arbiter can complete a disputed trade, even once the ruling is overdue
+    try {
+      await resolve(true);
+      expect.fail("the taker can only unwind");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("Unauthorized");
+    }
+
+    const makerABefore = (await provider.connection.getTokenAccountBalance(makerAtaA)).value.uiAmount;
+    const takerBBefore = (await provider.connection.getTokenAccountBalance(takerAtaB)).value.uiAmount;
+    await resolve(false);
+    const makerAAfter = (await provider.connection.getTokenAccountBalance(makerAtaA)).value.uiAmount;
+    const takerBAfter = (await provider.connection.getTokenAccountBalance(takerAtaB)).value.uiAmount;
+    expect(makerAAfter - makerABefore).to.equal(depositAmount);
+    expect(takerBAfter - takerBBefore).to.equal(receiveAmount + Math.ceil((receiveAmount * feeBps) / 10_000));
+    expect(await provider.connection.getAccountInfo(escrow)).to.be.null;
+  });
+
+  it("Releases milestone tranches and cancels the rest", async () => {
+    const approver = anchor.web3.Keypair.generate();
+    const milestones = [30, 30, 40].map((amount) => new anchor.BN(amount));
+    const milestoneEscrow = (seed: anchor.BN) => {
+      const [escrow] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), maker.toBuffer(), seed.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+      return { escrow, vault: getAssociatedTokenAddressSync(mintA, escrow, true) };
+    };
+    const balance = async (ata: anchor.web3.PublicKey) =>
+      (await provider.connection.getTokenAccountBalance(ata)).value.uiAmount;
+
+    // The beneficiary is the escrow's named taker, the tranches split the whole offer
+    const makeMilestones = async (seed: anchor.BN) =>
+      program.methods
+        .make(
+          seed,
+          new anchor.BN(100),
+          new anchor.BN(0),
+          await expiresIn(3600),
+          makeParams({ taker: taker.publicKey, milestones, approver: approver.publicKey })
+        )
+        .accountsStrict({
+          maker,
+          mintA,
+          mintB,
+          makerAtaA,
+          escrow: milestoneEscrow(seed).escrow,
+          offerBook: null,
+          bookPage: null,
+          vault: milestoneEscrow(seed).vault,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+    const release = (seed: anchor.BN, signer: anchor.web3.Keypair | null) =>
+      program.methods
+        .releaseMilestone()
+        .accountsStrict({
+          signer: signer ? signer.publicKey : maker,
+          maker,
+          beneficiary: taker.publicKey,
+          mintA,
+          beneficiaryAtaA: takerAtaA,
+          escrow: milestoneEscrow(seed).escrow,
+          vault: milestoneEscrow(seed).vault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers(signer ? [signer] : [])
+        .rpc();
+
+    const seed15 = new anchor.BN(15151);
+    await makeMilestones(seed15);
+    const { escrow, vault: milestoneVault } = milestoneEscrow(seed15);
+    expect(await balance(milestoneVault)).to.equal(100);
+
+    const takerBefore = await balance(takerAtaA);
+    await release(seed15, null);
+    await release(seed15, approver);
+    expect((await balance(takerAtaA)) - takerBefore).to.equal(60);
+    const escrowAccount = await program.account.escrow.fetch(escrow);
+    expect(escrowAccount.released).to.equal(2);
+    expect(escrowAccount.offer.toNumber()).to.equal(40);
+
+    // The beneficiary cannot sign off their own tranche
+    try {
+      await release(seed15, taker);
+      expect.fail("release should fail for the beneficiary");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("Unauthorized");
+    }
+
+    // Cancelling is a refund of what is left in the vault: the released tranches stay with
+    // the beneficiary
+    const makerBefore = await balance(makerAtaA);
+    await program.methods
+      .refund()
+      .accountsStrict({
+        maker,
+        mintA,
+        makerAtaA,
+        escrow,
+        bookPage: null,
+        vault: milestoneVault,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    expect((await balance(makerAtaA)) - makerBefore).to.equal(40);
+    expect((await balance(takerAtaA)) - takerBefore).to.equal(60);
+    expect(await provider.connection.getAccountInfo(escrow)).to.be.null;
+
+    // Signing off the last tranche pays it and closes the escrow
+    const fullSeed = new anchor.BN(15152);
+    await makeMilestones(fullSeed);
+    const takerBeforeAll = await balance(takerAtaA);
+    for (let i = 0; i < milestones.length; i++) {
+      await release(fullSeed, approver);
+    }
+    expect((await balance(takerAtaA)) - takerBeforeAll).to.equal(100);
+    expect(await provider.connection.getAccountInfo(milestoneEscrow(fullSeed).escrow)).to.be.null;
+  });
+
+  it("Claims a hash time-locked escrow with the preimage and refunds after timeout", async () => {
+    const preimage = randomBytes(32);
+    const hash = [...createHash("sha256").update(preimage).digest()];
+    const htlcAccounts = (seed: anchor.BN) => {
+      const [escrow] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), maker.toBuffer(), seed.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+      return { escrow, vault: getAssociatedTokenAddressSync(mintA, escrow, true) };
+    };
+    // The taker pays on the other chain, so nothing is asked for here
+    const makeHtlc = async (seed: anchor.BN, timeout: anchor.BN) => {
+      const { escrow, vault } = htlcAccounts(seed);
+      await program.methods
+        .make(seed, new anchor.BN(depositAmount), new anchor.BN(0), timeout, makeParams({ taker: taker.publicKey, hashLock: hash }))
+        .accountsStrict({
+          maker,
+          mintA,
+          mintB,
+          makerAtaA,
+          offerBook: null,
+          bookPage: null,
+          escrow,
+          vault,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+    };
+    const refundHtlc = (seed: anchor.BN) =>
+      program.methods
+        .refund()
+        .accountsStrict({
+          maker,
+          mintA,
+          makerAtaA,
+          bookPage: null,
+          ...htlcAccounts(seed),
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+    const balance = async (ata: anchor.web3.PublicKey) =>
+      (await provider.connection.getTokenAccountBalance(ata)).value.uiAmount;
+
+    const seed16 = new anchor.BN(16161);
+    await makeHtlc(seed16, await expiresIn(3600));
+    const takeHtlc = (secret: Buffer | null) =>
+      program.methods
+        .take(null, secret)
+        .accountsStrict({
+          taker: taker.publicKey,
+          maker,
+          mintA,
+          mintB,
+          takerAtaA,
+          takerAtaB,
+          makerAtaB,
+          config,
+          treasury,
+          treasuryAtaB,
+          bookPage: null,
+          ...htlcAccounts(seed16),
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([taker])
+        .rpc();
+
+    for (const secret of [randomBytes(32), null]) {
+      try {
+        await takeHtlc(secret);
+        expect.fail("take should fail without the preimage");
+      } catch (err) {
+        expect(err.error.errorCode.code).to.equal("InvalidPreimage");
+      }
+    }
+    // The maker cannot pull the tokens while the taker may still be claiming them
+    try {
+      await refundHtlc(seed16);
+      expect.fail("refund should fail before the timeout");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("NotExpired");
+    }
+
+    const takerBefore = await balance(takerAtaA);
+    await takeHtlc(preimage);
+    expect((await balance(takerAtaA)) - takerBefore).to.equal(depositAmount);
+    expect(await provider.connection.getAccountInfo(htlcAccounts(seed16).escrow)).to.be.null;
+
+    // The maker gets the tokens back once the lock times out unclaimed
+    const seed17 = new anchor.BN(17171);
+    await makeHtlc(seed17, await expiresIn(2));
+    await new Promise(resolve => setTimeout(resolve, 4000));
+
+    const makerBefore = await balance(makerAtaA);
+    await refundHtlc(seed17);
+    expect((await balance(makerAtaA)) - makerBefore).to.equal(depositAmount);
+    expect(await provider.connection.getAccountInfo(htlcAccounts(seed17).escrow)).to.be.null;
+  });
+
+  it("Sells at a declining Dutch auction price", async () => {
+    const seed18 = new anchor.BN(18181);
+    [escrowPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.toBuffer(), seed18.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    vault = getAssociatedTokenAddressSync(mintA, escrowPda, true);
+
+    // Started long enough ago that the price has already decayed to the floor
+    const startReceive = receiveAmount * 2;
+    const auction = {
+      endReceive: new anchor.BN(receiveAmount),
+      startAt: await expiresIn(-7200),
+      duration: new anchor.BN(3600),
+    };
+    await program.methods
+      .make(seed18, new anchor.BN(depositAmount), new anchor.BN(startReceive), await expiresIn(3600), makeParams({ auction }))
+      .accountsStrict({
+        maker: maker,
+        mintA: mintA,
+        mintB: mintB,
+        makerAtaA: makerAtaA,
+        offerBook,
+        bookPage,
+        escrow: escrowPda,
+        vault: vault,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    // Auctions only sell whole, so partial fills are rejected
+    try {
+      await program.methods
+        .takePartial(new anchor.BN(receiveAmount / 2), null)
+        .accountsStrict({
+          taker: taker.publicKey,
+          maker: maker,
+          mintA: mintA,
+          mintB: mintB,
+          takerAtaA: takerAtaA,
+          takerAtaB: takerAtaB,
+          makerAtaB: makerAtaB,
+          config,
+          treasury,
+          treasuryAtaB,
+          bookPage,
+          escrow: escrowPda,
+          vault: vault,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([taker])
+        .rpc();
+      expect.fail("take_partial should fail on an auction");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("AuctionUnsupported");
+    }
+
+    const makerBefore = (await provider.connection.getTokenAccountBalance(makerAtaB)).value.uiAmount;
+    await program.methods
+      .take(null, null)
+      .accountsStrict({
+        taker: taker.publicKey,
+        maker: maker,
+        mintA: mintA,
+        mintB: mintB,
+        takerAtaA: takerAtaA,
+        takerAtaB: takerAtaB,
+        makerAtaB: makerAtaB,
+        config,
+        treasury,
+        treasuryAtaB,
+        bookPage,
+        escrow: escrowPda,
+        vault: vault,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([taker])
+      .rpc();
+
+    const makerAfter = (await provider.connection.getTokenAccountBalance(makerAtaB)).value.uiAmount;
+    expect(makerAfter - makerBefore).to.equal(receiveAmount);
+    expect(await provider.connection.getAccountInfo(escrowPda)).to.be.null;
+  });
+
+  it("Takes on behalf of a taker from a signed intent", async () => {
+    const relayer = anchor.web3.Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(relayer.publicKey, anchor.web3.LAMPORTS_PER_SOL)
+    );
+
+    const seed19 = new anchor.BN(19191);
+    [escrowPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.toBuffer(), seed19.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    vault = getAssociatedTokenAddressSync(mintA, escrowPda, true);
+    await program.methods
+      .make(seed19, new anchor.BN(depositAmount), new anchor.BN(receiveAmount), await expiresIn(3600), makeParams())
+      .accountsStrict({
+        maker: maker,
+        mintA: mintA,
+        mintB: mintB,
+        makerAtaA: makerAtaA,
+        offerBook,
+        bookPage,
+        escrow: escrowPda,
+        vault: vault,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    // One-time approval of the program's delegate for the price plus the fee
+    const [delegate] = anchor.web3.PublicKey.findProgramAddressSync([Buffer.from("delegate")], program.programId);
+    await approve(provider.connection, provider.wallet.payer, takerAtaB, delegate, taker, receiveAmount * 2);
+
+    const intent = {
+      escrow: escrowPda,
+      mintA,
+      mintB,
+      revision: 0,
+      minOffer: new anchor.BN(depositAmount),
+      maxReceive: new anchor.BN(receiveAmount),
+      expiresAt: await expiresIn(600),
+    };
+    // Borsh encoding of TakeIntent
+    const message = Buffer.concat([
+      escrowPda.toBuffer(),
+      mintA.toBuffer(),
+      mintB.toBuffer(),
+      new anchor.BN(intent.revision).toArrayLike(Buffer, "le", 4),
+      intent.minOffer.toArrayLike(Buffer, "le", 8),
+      intent.maxReceive.toArrayLike(Buffer, "le", 8),
+      intent.expiresAt.toTwos(64).toArrayLike(Buffer, "le", 8),
+    ]);
+    const ed25519Ix = anchor.web3.Ed25519Program.createInstructionWithPrivateKey({
+      privateKey: taker.secretKey,
+      message,
+    });
+
+    const takeWithIntent = (maxReceive: anchor.BN) =>
+      program.methods
+        .takeWithIntent({ ...intent, maxReceive })
+        .accountsStrict({
+          relayer: relayer.publicKey,
+          taker: taker.publicKey,
+          maker,
+          mintA,
+          mintB,
+          takerAtaA,
+          takerAtaB,
+          makerAtaB,
+          config,
+          treasury,
+          treasuryAtaB,
+          bookPage,
+          escrow: escrowPda,
+          vault,
+          delegate,
+          instructionSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        })
+        .preInstructions([ed25519Ix])
+        .signers([relayer])
+        .rpc();
+
+    // The relayer cannot change the terms the taker signed
+    try {
+      await takeWithIntent(new anchor.BN(receiveAmount * 2));
+      expect.fail("take_with_intent should fail with altered terms");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidIntent");
+    }
+
+    const takerABefore = (await provider.connection.getTokenAccountBalance(takerAtaA)).value.uiAmount;
+    await takeWithIntent(intent.maxReceive);
+    const takerAAfter = (await provider.connection.getTokenAccountBalance(takerAtaA)).value.uiAmount;
+    expect(takerAAfter - takerABefore).to.equal(depositAmount);
+    expect(await provider.connection.getAccountInfo(escrowPda)).to.be.null;
+  });
+
+  it("Lets a taker counter-offer and the maker accept it", async () => {
+    const seed20 = new anchor.BN(20202);
+    [escrowPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.toBuffer(), seed20.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    vault = getAssociatedTokenAddressSync(mintA, escrowPda, true);
+    await program.methods
+      .make(seed20, new anchor.BN(depositAmount), new anchor.BN(receiveAmount), await expiresIn(3600), makeParams())
+      .accountsStrict({
+        maker: maker,
+        mintA: mintA,
+        mintB: mintB,
+        makerAtaA: makerAtaA,
+        offerBook,
+        bookPage,
+        escrow: escrowPda,
+        vault: vault,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const [counter] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("counter"), escrowPda.toBuffer(), taker.publicKey.toBuffer()],
+      program.programId
+    );
+    const counterVault = getAssociatedTokenAddressSync(mintB, counter, true);
+    const counterReceive = receiveAmount / 2;
+    const counterFee = Math.ceil((counterReceive * feeBps) / 10_000);
+    const balance = async (ata: anchor.web3.PublicKey) =>
+      (await provider.connection.getTokenAccountBalance(ata)).value.uiAmount;
+    const makeCounter = () =>
+      program.methods
+        .makeCounter(new anchor.BN(counterReceive))
+        .accountsStrict({
+          taker: taker.publicKey,
+          mintB,
+          takerAtaB,
+          config,
+          escrow: escrowPda,
+          counter,
+          counterVault,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([taker])
+        .rpc();
+
+    // Post and withdraw: the taker gets the deposit and fee back
+    const takerBBefore = await balance(takerAtaB);
+    await makeCounter();
+    expect(await balance(counterVault)).to.equal(counterReceive + counterFee);
+    await program.methods
+      .withdrawCounter()
+      .accountsStrict({
+        taker: taker.publicKey,
+        mintB,
+        takerAtaB,
+        counter,
+        counterVault,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([taker])
+      .rpc();
+    expect(await balance(takerAtaB)).to.equal(takerBBefore);
+    expect(await provider.connection.getAccountInfo(counter)).to.be.null;
+
+    // Post again and have the maker accept. The counter names the escrow's mint A and seed,
+    // so it can't be accepted against another escrow made again at the same address
+    await makeCounter();
+    const counterAccount = await program.account.counterOffer.fetch(counter);
+    expect(counterAccount.mintA.toBase58()).to.equal(mintA.toBase58());
+    expect(counterAccount.seed.toString()).to.equal(seed20.toString());
+
+    // A donation to the counter's vault can't keep it from closing, it goes to the maker
+    const donation = 3;
+    await transfer(provider.connection, taker, takerAtaB, counterVault, taker, donation);
+    const makerBBefore = await balance(makerAtaB);
+    const takerABefore = await balance(takerAtaA);
+    await program.methods
+      .acceptCounter()
+      .accountsStrict({
+        maker,
+        taker: taker.publicKey,
+        mintA,
+        mintB,
+        takerAtaA,
+        makerAtaB,
+        config,
+        treasury,
+        treasuryAtaB,
+        bookPage,
+        escrow: escrowPda,
+        vault,
+        counter,
+        counterVault,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+
+    expect((await balance(makerAtaB)) - makerBBefore).to.equal(counterReceive + donation);
+    expect((await balance(takerAtaA)) - takerABefore).to.equal(depositAmount);
+    expect(await provider.connection.getAccountInfo(escrowPda)).to.be.null;
+    expect(await provider.connection.getAccountInfo(counter)).to.be.null;
+    expect(await provider.connection.getAccountInfo(counterVault)).to.be.null;
+  });
+
+  it("Indexes open escrows in the pair's offer book pages", async () => {
+    const escrowFor = (seed: anchor.BN) =>
+      anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), maker.toBuffer(), seed.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      )[0];
+    const pageFor = (page: number) =>
+      anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("book_page"), mintA.toBuffer(), mintB.toBuffer(), pageSeed(page)],
+        program.programId
+      )[0];
+    const listed = async (page: number) =>
+      (await program.account.bookPage.fetch(pageFor(page))).entries.map((entry) => entry.escrow.toBase58());
+    const setMinOffer = (minOffer: number) =>
+      program.methods
+        .setBookMinOffer(new anchor.BN(minOffer))
+        .accountsStrict({
+          authority: maker,
+          config,
+          mintA,
+          mintB,
+          offerBook,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+    const make = async (seed: anchor.BN, offer: number, page: number, auction = null, lifetime = 3600) =>
+      program.methods
+        .make(seed, new anchor.BN(offer), new anchor.BN(receiveAmount), await expiresIn(lifetime), makeParams({ auction, page }))
+        .accountsStrict({
+          maker: maker,
+          mintA: mintA,
+          mintB: mintB,
+          makerAtaA: makerAtaA,
+          escrow: escrowFor(seed),
+          offerBook,
+          bookPage: pageFor(page),
+          vault: getAssociatedTokenAddressSync(mintA, escrowFor(seed), true),
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+    await mintTo(provider.connection, provider.wallet.payer, mintA, makerAtaA, provider.wallet.payer, depositAmount * 2);
+
+    // Dust offers below the pair's minimum cannot be listed
+    await setMinOffer(depositAmount / 2);
+    try {
+      await make(new anchor.BN(21000), depositAmount / 10, 0);
+      expect.fail("make should reject an offer below the book minimum");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("OfferTooSmall");
+    }
+
+    // Pages open one after another, so page 2 is out of reach until page 1 exists
+    const onFirstPage = new anchor.BN(21001);
+    const onSecondPage = new anchor.BN(21002);
+    try {
+      await make(onSecondPage, depositAmount, 2);
+      expect.fail("make should reject a page that is not open yet");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidBookPage");
+    }
+    const auction = {
+      endReceive: new anchor.BN(receiveAmount / 2),
+      startAt: await expiresIn(0),
+      duration: new anchor.BN(3600),
+    };
+    await make(onFirstPage, depositAmount, 0, auction);
+    await make(onSecondPage, depositAmount, 1);
+
+    expect(await listed(0)).to.include(escrowFor(onFirstPage).toBase58());
+    expect(await listed(1)).to.deep.equal([escrowFor(onSecondPage).toBase58()]);
+    expect((await program.account.offerBook.fetch(offerBook)).pages).to.equal(2);
+    // Auction entries carry their schedule so clients can rank them by the current price
+    const entry = (await program.account.bookPage.fetch(pageFor(0))).entries.find((entry) =>
+      entry.escrow.equals(escrowFor(onFirstPage))
+    );
+    expect(entry.auction.endReceive.toNumber()).to.equal(receiveAmount / 2);
+    expect(entry.expiresAt.toNumber()).to.equal((await program.account.escrow.fetch(escrowFor(onFirstPage))).expiresAt.toNumber());
+
+    for (const [seed, page] of [[onFirstPage, 0], [onSecondPage, 1]] as const) {
+      await program.methods
+        .refund()
+        .accountsStrict({
+          maker: maker,
+          mintA: mintA,
+          makerAtaA: makerAtaA,
+          escrow: escrowFor(seed),
+          bookPage: pageFor(page),
+          vault: getAssociatedTokenAddressSync(mintA, escrowFor(seed), true),
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+    }
+    expect(await listed(0)).to.not.include(escrowFor(onFirstPage).toBase58());
+    expect(await listed(1)).to.be.empty;
+    await setMinOffer(0);
+
+    // A full page makes room by dropping offers that expired without anyone cleaning them up
+    const pageEntries = 32;
+    const expiring = Array.from({ length: pageEntries }, (_, i) => new anchor.BN(21100 + i));
+    await mintTo(provider.connection, provider.wallet.payer, mintA, makerAtaA, provider.wallet.payer, pageEntries + 1);
+    for (const seed of expiring) {
+      await make(seed, 1, 1, null, 2);
+    }
+    expect(await listed(1)).to.have.lengthOf(pageEntries);
+
+    await new Promise(resolve => setTimeout(resolve, 4000));
+    const latecomer = new anchor.BN(21200);
+    await make(latecomer, 1, 1);
+    expect(await listed(1)).to.deep.equal([escrowFor(latecomer).toBase58()]);
+
+    // Dropped offers are still open and expire as usual without their page entry
+    await program.methods
+      .expire()
+      .accountsStrict({
+        cranker: taker.publicKey,
+        maker: maker,
+        mintA: mintA,
+        makerAtaA: makerAtaA,
+        bookPage: pageFor(1),
+        escrow: escrowFor(expiring[0]),
+        vault: getAssociatedTokenAddressSync(mintA, escrowFor(expiring[0]), true),
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([taker])
+      .rpc();
+    expect(await provider.connection.getAccountInfo(escrowFor(expiring[0]))).to.be.null;
+    expect(await listed(1)).to.deep.equal([escrowFor(latecomer).toBase58()]);
+  });
+
+  it("Sweeps several escrows in one transaction within a spend limit", async () => {
+    const escrowFor = (seed: anchor.BN) =>
+      anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), maker.toBuffer(), seed.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      )[0];
+    const balance = async (ata: anchor.web3.PublicKey) =>
+      (await provider.connection.getTokenAccountBalance(ata)).value.uiAmount;
+
+    await mintTo(provider.connection, provider.wallet.payer, mintA, makerAtaA, provider.wallet.payer, depositAmount * 2);
+    const seeds = [new anchor.BN(22001), new anchor.BN(22002)];
+    const receives = [receiveAmount, receiveAmount / 2];
+    for (const [i, seed] of seeds.entries()) {
+      await program.methods
+        .make(seed, new anchor.BN(depositAmount), new anchor.BN(receives[i]), await expiresIn(3600), makeParams())
+        .accountsStrict({
+          maker: maker,
+          mintA: mintA,
+          mintB: mintB,
+          makerAtaA: makerAtaA,
+          escrow: escrowFor(seed),
+          offerBook,
+          bookPage,
+          vault: getAssociatedTokenAddressSync(mintA, escrowFor(seed), true),
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+    }
+
+    const remainingAccounts = seeds.flatMap((seed) => [
+      { pubkey: escrowFor(seed), isSigner: false, isWritable: true },
+      { pubkey: getAssociatedTokenAddressSync(mintA, escrowFor(seed), true), isSigner: false, isWritable: true },
+      { pubkey: maker, isSigner: false, isWritable: true },
+      { pubkey: makerAtaB, isSigner: false, isWritable: true },
+      { pubkey: bookPage, isSigner: false, isWritable: true },
+    ]);
+    const totalSpend = receives.reduce((sum, receive) => sum + receive + Math.ceil((receive * feeBps) / 10_000), 0);
+    const takeMany = (maxSpend: number, revisions = seeds.map(() => 0)) =>
+      program.methods
+        .takeMany(new anchor.BN(maxSpend), new anchor.BN(depositAmount * seeds.length), revisions)
+        .accountsStrict({
+          taker: taker.publicKey,
+          mintA,
+          mintB,
+          takerAtaA,
+          takerAtaB,
+          config,
+          treasury,
+          treasuryAtaB,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(remainingAccounts)
+        .signers([taker])
+        .rpc();
+
+    // One short of the total rejects the whole batch, leaving both escrows open
+    try {
+      await takeMany(totalSpend - 1);
+      expect.fail("take_many should exceed the spend limit");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("MaxSpendExceeded");
+    }
+    expect(await provider.connection.getAccountInfo(escrowFor(seeds[0]))).to.not.be.null;
+
+    // A maker shrinking the offer after the taker priced the batch bumps the revision
+    const vault = getAssociatedTokenAddressSync(mintA, escrowFor(seeds[1]), true);
+    const reprice = (offer: number) =>
+      program.methods
+        .updateOffer(new anchor.BN(offer), new anchor.BN(receives[1]))
+        .accountsStrict({
+          maker: maker,
+          mintA: mintA,
+          makerAtaA: makerAtaA,
+          offerBook,
+          bookPage,
+          escrow: escrowFor(seeds[1]),
+          vault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+    await reprice(1);
+    try {
+      await takeMany(totalSpend);
+      expect.fail("take_many should reject a repriced escrow");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("RevisionMismatch");
+    }
+    // Pinning the new revision still leaves the batch short of the token A the taker asked for
+    try {
+      await takeMany(totalSpend, [0, 1]);
+      expect.fail("take_many should fall short of min_receive_a");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("MinReceiveNotMet");
+    }
+    await reprice(depositAmount);
+
+    const takerABefore = await balance(takerAtaA);
+    const takerBBefore = await balance(takerAtaB);
+    await takeMany(totalSpend, [0, 2]);
+
+    expect((await balance(takerAtaA)) - takerABefore).to.equal(depositAmount * seeds.length);
+    expect(takerBBefore - (await balance(takerAtaB))).to.equal(totalSpend);
+    const entries = (await program.account.bookPage.fetch(bookPage)).entries.map((entry) => entry.escrow.toBase58());
+    for (const seed of seeds) {
+      expect(await provider.connection.getAccountInfo(escrowFor(seed))).to.be.null;
+      expect(entries).to.not.include(escrowFor(seed).toBase58());
+    }
   });
-});
\ No newline at end of file
+});